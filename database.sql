@@ -2,13 +2,32 @@ SET default_tablespace = '';
 
 SET default_table_access_method = heap;
 
+CREATE TYPE public.scheduler AS ENUM (
+    'leitner',
+    'sm2'
+);
+ALTER TYPE public.scheduler OWNER TO kino;
+
+CREATE TYPE public.review_grade AS ENUM (
+    'again',
+    'hard',
+    'good',
+    'easy'
+);
+ALTER TYPE public.review_grade OWNER TO kino;
+
 CREATE TABLE public.cards (
     id bigint NOT NULL,
     owner_id bigint NOT NULL,
     deck_id bigint NOT NULL,
     front bigint NOT NULL,
     back bigint[] NOT NULL,
-    done_at timestamp without time zone
+    done_at timestamp without time zone,
+    due_at timestamp without time zone,
+    ease double precision DEFAULT 2.5 NOT NULL,
+    interval_days double precision DEFAULT 0 NOT NULL,
+    repetitions integer DEFAULT 0 NOT NULL,
+    lapses integer DEFAULT 0 NOT NULL
 );
 ALTER TABLE public.cards OWNER TO kino;
 
@@ -17,7 +36,11 @@ CREATE TABLE public.decks (
     owner_id bigint NOT NULL,
     card_count bigint DEFAULT 0 NOT NULL,
     "interval" interval NOT NULL,
-    level integer NOT NULL
+    level integer NOT NULL,
+    scheduler public.scheduler DEFAULT 'sm2'::public.scheduler NOT NULL,
+    name character varying(64) DEFAULT ''::character varying NOT NULL,
+    description character varying(256),
+    "position" integer DEFAULT 0 NOT NULL
 );
 ALTER TABLE public.decks OWNER TO kino;
 
@@ -37,21 +60,111 @@ CREATE TABLE public.faces (
 );
 ALTER TABLE public.faces OWNER TO kino;
 
+-- Review history. Card state before each review is kept to undo reviews. Grade is NULL for
+-- ungraded reviews such as marking a card done or moving it.
+CREATE TABLE public.reviews (
+    id bigint NOT NULL,
+    card_id bigint NOT NULL,
+    owner_id bigint NOT NULL,
+    reviewed_at timestamp without time zone NOT NULL,
+    grade public.review_grade,
+    elapsed_ms integer,
+    previous_interval_days double precision NOT NULL,
+    next_interval_days double precision NOT NULL,
+    previous_deck_id bigint NOT NULL,
+    previous_done_at timestamp without time zone,
+    previous_due_at timestamp without time zone,
+    previous_ease double precision NOT NULL,
+    previous_repetitions integer NOT NULL,
+    previous_lapses integer NOT NULL
+);
+ALTER TABLE public.reviews OWNER TO kino;
+
+-- Accounts at OpenID Connect providers that users sign in with. Issuer is the first issuer of
+-- the provider, so that it stays the same for issuers with several names.
+CREATE TABLE public.identities (
+    id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    issuer text NOT NULL,
+    subject text NOT NULL,
+    email character varying(254),
+    created_at timestamp without time zone NOT NULL
+);
+ALTER TABLE public.identities OWNER TO kino;
+
+-- Opaque refresh tokens, only hashes of secrets are kept. Tokens rotated from the same sign in
+-- share a session. Used tokens are kept until they expire to detect reuse.
+CREATE TABLE public.refresh_tokens (
+    id bigint NOT NULL,
+    session_id bigint NOT NULL,
+    secret_hash bytea NOT NULL,
+    expires_at timestamp without time zone NOT NULL,
+    used_at timestamp without time zone
+);
+ALTER TABLE public.refresh_tokens OWNER TO kino;
+
+-- Sign in sessions, they last while their refresh tokens are rotated.
+CREATE TABLE public.sessions (
+    id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    created_at timestamp without time zone NOT NULL,
+    last_seen_at timestamp without time zone NOT NULL,
+    user_agent character varying(256),
+    ip character varying(45)
+);
+ALTER TABLE public.sessions OWNER TO kino;
+
 CREATE TABLE public.users (
     id bigint NOT NULL,
     email character varying(254) NOT NULL,
-    google_id text NOT NULL,
     username character varying(24),
     name text,
     picture text
 );
 ALTER TABLE public.users OWNER TO kino;
 
+-- Date of a timestamp in server time zone, as seen in another time zone.
+CREATE FUNCTION public.local_date(ts timestamp without time zone, tz text) RETURNS date
+    LANGUAGE sql STABLE
+    AS $$ SELECT ((ts AT TIME ZONE current_setting('TimeZone')) AT TIME ZONE tz)::date $$;
+ALTER FUNCTION public.local_date(timestamp without time zone, text) OWNER TO kino;
 
-COPY public.cards (id, owner_id, deck_id, front, back, done_at) FROM stdin;
+-- Due time of reviewed cards. Leitner cards are due after their deck's interval.
+CREATE VIEW public.card_schedules AS
+    SELECT
+        cards.id,
+        cards.owner_id,
+        cards.deck_id,
+        CASE
+            WHEN cards.done_at IS NULL THEN NULL
+            WHEN decks.scheduler = 'sm2' THEN COALESCE(cards.due_at, cards.done_at)
+            ELSE cards.done_at + decks."interval"
+        END AS due_at
+    FROM public.cards
+    JOIN public.decks ON decks.id = cards.deck_id;
+ALTER VIEW public.card_schedules OWNER TO kino;
+
+-- Keeps card_count of decks in sync with cards.
+CREATE FUNCTION public.update_card_count() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE public.decks SET card_count = card_count - 1 WHERE id = OLD.deck_id;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE public.decks SET card_count = card_count + 1 WHERE id = NEW.deck_id;
+    END IF;
+    RETURN NULL;
+END
+$$;
+ALTER FUNCTION public.update_card_count() OWNER TO kino;
+
+
+COPY public.cards (id, owner_id, deck_id, front, back, done_at, due_at, ease, interval_days, repetitions, lapses) FROM stdin;
 \.
 
-COPY public.decks (id, owner_id, card_count, "interval", level) FROM stdin;
+COPY public.decks (id, owner_id, card_count, "interval", level, scheduler, name, description, "position") FROM stdin;
 \.
 
 
@@ -66,7 +179,19 @@ COPY public.extensions (id, owner_id, name, data) FROM stdin;
 COPY public.faces (id, owner_id, extension_id, data) FROM stdin;
 \.
 
-COPY public.users (id, email, google_id, username, name, picture) FROM stdin;
+COPY public.reviews (id, card_id, owner_id, reviewed_at, grade, elapsed_ms, previous_interval_days, next_interval_days, previous_deck_id, previous_done_at, previous_due_at, previous_ease, previous_repetitions, previous_lapses) FROM stdin;
+\.
+
+COPY public.identities (id, user_id, issuer, subject, email, created_at) FROM stdin;
+\.
+
+COPY public.refresh_tokens (id, session_id, secret_hash, expires_at, used_at) FROM stdin;
+\.
+
+COPY public.sessions (id, user_id, created_at, last_seen_at, user_agent, ip) FROM stdin;
+\.
+
+COPY public.users (id, email, username, name, picture) FROM stdin;
 \.
 
 
@@ -82,11 +207,33 @@ ALTER TABLE ONLY public.extensions
 ALTER TABLE ONLY public.faces
     ADD CONSTRAINT faces_pkey PRIMARY KEY (id);
 
+ALTER TABLE ONLY public.reviews
+    ADD CONSTRAINT reviews_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.identities
+    ADD CONSTRAINT identities_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.refresh_tokens
+    ADD CONSTRAINT refresh_tokens_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.sessions
+    ADD CONSTRAINT sessions_pkey PRIMARY KEY (id);
+
 ALTER TABLE ONLY public.users
     ADD CONSTRAINT users_pkey PRIMARY KEY (id);
 
 
-CREATE UNIQUE INDEX users_google_id ON public.users USING btree (google_id) INCLUDE (username) WITH (deduplicate_items='true');
+CREATE UNIQUE INDEX identities_issuer_subject ON public.identities USING btree (issuer, subject);
+
+CREATE INDEX identities_user_id ON public.identities USING btree (user_id);
+
+CREATE INDEX reviews_card_id ON public.reviews USING btree (card_id, reviewed_at);
+
+CREATE INDEX reviews_owner_id ON public.reviews USING btree (owner_id, reviewed_at);
+
+CREATE INDEX refresh_tokens_session_id ON public.refresh_tokens USING btree (session_id);
+
+CREATE INDEX sessions_user_id ON public.sessions USING btree (user_id);
 
 CREATE UNIQUE INDEX users_username ON public.users USING btree (username) WITH (deduplicate_items='true');
 
@@ -105,3 +252,20 @@ ALTER TABLE ONLY public.faces
 
 ALTER TABLE ONLY public.faces
     ADD CONSTRAINT faces_owner_id_fk FOREIGN KEY (owner_id) REFERENCES public.users(id) NOT VALID;
+
+ALTER TABLE ONLY public.identities
+    ADD CONSTRAINT identities_user_id_fk FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE;
+
+ALTER TABLE ONLY public.refresh_tokens
+    ADD CONSTRAINT refresh_tokens_session_id_fk FOREIGN KEY (session_id) REFERENCES public.sessions(id) ON DELETE CASCADE;
+
+ALTER TABLE ONLY public.sessions
+    ADD CONSTRAINT sessions_user_id_fk FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE;
+
+ALTER TABLE ONLY public.reviews
+    ADD CONSTRAINT reviews_card_id_fk FOREIGN KEY (card_id) REFERENCES public.cards(id) ON DELETE CASCADE;
+
+
+CREATE TRIGGER cards_card_count AFTER INSERT OR DELETE ON public.cards FOR EACH ROW EXECUTE FUNCTION public.update_card_count();
+
+CREATE TRIGGER cards_card_count_move AFTER UPDATE OF deck_id ON public.cards FOR EACH ROW WHEN (OLD.deck_id IS DISTINCT FROM NEW.deck_id) EXECUTE FUNCTION public.update_card_count();
@@ -0,0 +1,24 @@
+-- Adds SM-2 scheduling of cards. Decks created before keep Leitner scheduling.
+BEGIN;
+
+DO $$ BEGIN
+    CREATE TYPE public.scheduler AS ENUM (
+        'leitner',
+        'sm2'
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+ALTER TYPE public.scheduler OWNER TO kino;
+
+ALTER TABLE public.cards
+    ADD COLUMN IF NOT EXISTS due_at timestamp without time zone,
+    ADD COLUMN IF NOT EXISTS ease double precision DEFAULT 2.5 NOT NULL,
+    ADD COLUMN IF NOT EXISTS interval_days double precision DEFAULT 0 NOT NULL,
+    ADD COLUMN IF NOT EXISTS repetitions integer DEFAULT 0 NOT NULL,
+    ADD COLUMN IF NOT EXISTS lapses integer DEFAULT 0 NOT NULL;
+
+ALTER TABLE public.decks
+    ADD COLUMN IF NOT EXISTS scheduler public.scheduler DEFAULT 'leitner'::public.scheduler NOT NULL;
+ALTER TABLE public.decks ALTER COLUMN scheduler SET DEFAULT 'sm2'::public.scheduler;
+
+COMMIT;
@@ -0,0 +1,27 @@
+-- Adds the review queue, its due times and local dates of daily limits.
+BEGIN;
+
+ALTER TABLE public.cards ADD COLUMN IF NOT EXISTS learned_at timestamp without time zone;
+
+-- Date of a timestamp in server time zone, as seen in another time zone.
+CREATE OR REPLACE FUNCTION public.local_date(ts timestamp without time zone, tz text) RETURNS date
+    LANGUAGE sql STABLE
+    AS $$ SELECT ((ts AT TIME ZONE current_setting('TimeZone')) AT TIME ZONE tz)::date $$;
+ALTER FUNCTION public.local_date(timestamp without time zone, text) OWNER TO kino;
+
+-- Due time of reviewed cards. Leitner cards are due after their deck's interval.
+CREATE OR REPLACE VIEW public.card_schedules AS
+    SELECT
+        cards.id,
+        cards.owner_id,
+        cards.deck_id,
+        CASE
+            WHEN cards.done_at IS NULL THEN NULL
+            WHEN decks.scheduler = 'sm2' THEN COALESCE(cards.due_at, cards.done_at)
+            ELSE cards.done_at + decks."interval"
+        END AS due_at
+    FROM public.cards
+    JOIN public.decks ON decks.id = cards.deck_id;
+ALTER VIEW public.card_schedules OWNER TO kino;
+
+COMMIT;
@@ -0,0 +1,41 @@
+-- Adds review history, which replaces learned_at of cards in daily limits.
+BEGIN;
+
+DO $$ BEGIN
+    CREATE TYPE public.review_grade AS ENUM (
+        'again',
+        'hard',
+        'good',
+        'easy'
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+ALTER TYPE public.review_grade OWNER TO kino;
+
+CREATE TABLE IF NOT EXISTS public.reviews (
+    id bigint NOT NULL,
+    card_id bigint NOT NULL,
+    owner_id bigint NOT NULL,
+    reviewed_at timestamp without time zone NOT NULL,
+    grade public.review_grade,
+    elapsed_ms integer,
+    previous_interval_days double precision NOT NULL,
+    next_interval_days double precision NOT NULL,
+    previous_deck_id bigint NOT NULL,
+    previous_done_at timestamp without time zone,
+    previous_due_at timestamp without time zone,
+    previous_ease double precision NOT NULL,
+    previous_repetitions integer NOT NULL,
+    previous_lapses integer NOT NULL,
+    CONSTRAINT reviews_pkey PRIMARY KEY (id),
+    CONSTRAINT reviews_card_id_fk FOREIGN KEY (card_id) REFERENCES public.cards(id) ON DELETE CASCADE
+);
+ALTER TABLE public.reviews OWNER TO kino;
+
+CREATE INDEX IF NOT EXISTS reviews_card_id ON public.reviews USING btree (card_id, reviewed_at);
+
+CREATE INDEX IF NOT EXISTS reviews_owner_id ON public.reviews USING btree (owner_id, reviewed_at);
+
+ALTER TABLE public.cards DROP COLUMN IF EXISTS learned_at;
+
+COMMIT;
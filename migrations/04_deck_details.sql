@@ -0,0 +1,25 @@
+-- Adds names, descriptions and order of decks. Decks created before are ordered by creation.
+BEGIN;
+
+ALTER TABLE public.decks
+    ADD COLUMN IF NOT EXISTS name character varying(64) DEFAULT ''::character varying NOT NULL,
+    ADD COLUMN IF NOT EXISTS description character varying(256);
+
+DO $$ BEGIN
+    IF NOT EXISTS (
+        SELECT FROM information_schema.columns
+        WHERE table_schema = 'public' AND table_name = 'decks' AND column_name = 'position'
+    ) THEN
+        ALTER TABLE public.decks ADD COLUMN "position" integer DEFAULT 0 NOT NULL;
+
+        UPDATE public.decks
+        SET "position" = ordered.position - 1
+        FROM (
+            SELECT id, row_number() OVER (PARTITION BY owner_id ORDER BY id) AS position
+            FROM public.decks
+        ) AS ordered
+        WHERE decks.id = ordered.id;
+    END IF;
+END $$;
+
+COMMIT;
@@ -0,0 +1,35 @@
+-- Keeps card_count of decks in sync with cards and corrects counts that drifted before.
+BEGIN;
+
+-- Keeps card_count of decks in sync with cards.
+CREATE OR REPLACE FUNCTION public.update_card_count() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE public.decks SET card_count = card_count - 1 WHERE id = OLD.deck_id;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE public.decks SET card_count = card_count + 1 WHERE id = NEW.deck_id;
+    END IF;
+    RETURN NULL;
+END
+$$;
+ALTER FUNCTION public.update_card_count() OWNER TO kino;
+
+-- creating triggers blocks writes to cards until commit, so that the recount below is exact
+CREATE OR REPLACE TRIGGER cards_card_count AFTER INSERT OR DELETE ON public.cards FOR EACH ROW EXECUTE FUNCTION public.update_card_count();
+
+CREATE OR REPLACE TRIGGER cards_card_count_move AFTER UPDATE OF deck_id ON public.cards FOR EACH ROW WHEN (OLD.deck_id IS DISTINCT FROM NEW.deck_id) EXECUTE FUNCTION public.update_card_count();
+
+UPDATE public.decks
+SET card_count = counts.card_count
+FROM (
+    SELECT decks.id, COUNT(cards.id) AS card_count
+    FROM public.decks
+    LEFT JOIN public.cards ON cards.deck_id = decks.id
+    GROUP BY decks.id
+) AS counts
+WHERE decks.id = counts.id AND decks.card_count <> counts.card_count;
+
+COMMIT;
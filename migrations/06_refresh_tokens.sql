@@ -0,0 +1,24 @@
+-- Adds refresh tokens. Skipped once sessions have replaced token families.
+BEGIN;
+
+DO $$ BEGIN
+    IF to_regclass('public.refresh_tokens') IS NULL THEN
+        CREATE TABLE public.refresh_tokens (
+            id bigint NOT NULL,
+            family_id bigint NOT NULL,
+            user_id bigint NOT NULL,
+            secret_hash bytea NOT NULL,
+            expires_at timestamp without time zone NOT NULL,
+            used_at timestamp without time zone,
+            CONSTRAINT refresh_tokens_pkey PRIMARY KEY (id),
+            CONSTRAINT refresh_tokens_user_id_fk FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE
+        );
+        ALTER TABLE public.refresh_tokens OWNER TO kino;
+
+        CREATE INDEX refresh_tokens_family_id ON public.refresh_tokens USING btree (family_id);
+
+        CREATE INDEX refresh_tokens_user_id ON public.refresh_tokens USING btree (user_id);
+    END IF;
+END $$;
+
+COMMIT;
@@ -0,0 +1,55 @@
+-- Adds sign in sessions. Refresh token families become sessions, so that users stay signed in.
+BEGIN;
+
+CREATE TABLE IF NOT EXISTS public.sessions (
+    id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    created_at timestamp without time zone NOT NULL,
+    last_seen_at timestamp without time zone NOT NULL,
+    user_agent character varying(256),
+    ip character varying(45),
+    CONSTRAINT sessions_pkey PRIMARY KEY (id),
+    CONSTRAINT sessions_user_id_fk FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE
+);
+ALTER TABLE public.sessions OWNER TO kino;
+
+CREATE INDEX IF NOT EXISTS sessions_user_id ON public.sessions USING btree (user_id);
+
+DO $$ BEGIN
+    IF EXISTS (
+        SELECT FROM information_schema.columns
+        WHERE table_schema = 'public' AND table_name = 'refresh_tokens' AND column_name = 'family_id'
+    ) THEN
+        INSERT INTO public.sessions (id, user_id, created_at, last_seen_at)
+        SELECT family_id, user_id, LOCALTIMESTAMP, LOCALTIMESTAMP
+        FROM public.refresh_tokens
+        GROUP BY family_id, user_id;
+
+        -- recreated rather than altered, so that columns keep the order of a new database
+        ALTER TABLE public.refresh_tokens RENAME TO refresh_token_families;
+        ALTER TABLE public.refresh_token_families
+            DROP CONSTRAINT refresh_tokens_pkey,
+            DROP CONSTRAINT refresh_tokens_user_id_fk;
+
+        CREATE TABLE public.refresh_tokens (
+            id bigint NOT NULL,
+            session_id bigint NOT NULL,
+            secret_hash bytea NOT NULL,
+            expires_at timestamp without time zone NOT NULL,
+            used_at timestamp without time zone,
+            CONSTRAINT refresh_tokens_pkey PRIMARY KEY (id),
+            CONSTRAINT refresh_tokens_session_id_fk FOREIGN KEY (session_id) REFERENCES public.sessions(id) ON DELETE CASCADE
+        );
+        ALTER TABLE public.refresh_tokens OWNER TO kino;
+
+        INSERT INTO public.refresh_tokens (id, session_id, secret_hash, expires_at, used_at)
+        SELECT id, family_id, secret_hash, expires_at, used_at
+        FROM public.refresh_token_families;
+
+        DROP TABLE public.refresh_token_families;
+
+        CREATE INDEX refresh_tokens_session_id ON public.refresh_tokens USING btree (session_id);
+    END IF;
+END $$;
+
+COMMIT;
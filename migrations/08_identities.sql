@@ -0,0 +1,41 @@
+-- Moves Google accounts of users to identities. Skipped once users.google_id is dropped.
+BEGIN;
+
+DO $$ BEGIN
+    IF EXISTS (
+        SELECT FROM information_schema.columns
+        WHERE table_schema = 'public' AND table_name = 'users' AND column_name = 'google_id'
+    ) THEN
+        CREATE TABLE public.identities (
+            id bigint NOT NULL,
+            user_id bigint NOT NULL,
+            issuer text NOT NULL,
+            subject text NOT NULL,
+            email character varying(254),
+            created_at timestamp without time zone NOT NULL
+        );
+        ALTER TABLE public.identities OWNER TO kino;
+
+        -- user ids are unused by identities, so they are kept as identity ids
+        INSERT INTO public.identities (id, user_id, issuer, subject, email, created_at)
+        SELECT id, id, 'https://accounts.google.com', google_id, email, LOCALTIMESTAMP
+        FROM public.users;
+
+        ALTER TABLE ONLY public.identities
+            ADD CONSTRAINT identities_pkey PRIMARY KEY (id);
+
+        CREATE UNIQUE INDEX identities_issuer_subject ON public.identities USING btree (issuer, subject);
+
+        CREATE INDEX identities_user_id ON public.identities USING btree (user_id);
+
+        ALTER TABLE ONLY public.identities
+            ADD CONSTRAINT identities_user_id_fk FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE CASCADE;
+
+        -- dropped only after Google accounts are copied
+        DROP INDEX public.users_google_id;
+
+        ALTER TABLE public.users DROP COLUMN google_id;
+    END IF;
+END $$;
+
+COMMIT;
@@ -0,0 +1,698 @@
+use crate::{api::error::api_error, dicts::WordNetDatabase};
+
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read, Write},
+    path::PathBuf,
+};
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use serde::Deserialize;
+
+use serde_json::json;
+
+/// Maximum size of an uploaded package in bytes.
+pub const PACKAGE_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Maximum decompressed size of the collection in bytes.
+const COLLECTION_LIMIT: u64 = 256 * 1024 * 1024;
+
+/// Maximum decompressed size of the media list in bytes.
+const MEDIA_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Contents of an Anki `.apkg` package that are imported.
+#[derive(Debug, Default)]
+pub struct AnkiPackage {
+    pub decks: Vec<AnkiDeck>,
+    pub notes: Vec<AnkiNote>,
+    /// Number of media files, they are not imported.
+    pub media: usize,
+}
+
+#[derive(Debug)]
+pub struct AnkiDeck {
+    pub id: i64,
+    /// Full name, subdecks are separated by `::`.
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct AnkiNote {
+    pub id: i64,
+    /// Deck of the first card of the note.
+    pub deck_id: i64,
+    /// Fields as plain text.
+    pub fields: Vec<String>,
+    /// `None` for new and learning cards.
+    pub review: Option<AnkiReview>,
+}
+
+/// Scheduling state of a card in review.
+#[derive(Debug, Clone, Copy)]
+pub struct AnkiReview {
+    /// Due time in seconds since Unix epoch.
+    pub due: i64,
+    pub interval_days: f64,
+    pub ease: f64,
+    pub repetitions: i32,
+    pub lapses: i32,
+}
+
+/// Package reading error.
+#[derive(Debug)]
+pub enum AnkiError {
+    InvalidArchive,
+    /// Only `.apkg` files with `collection.anki21` or `collection.anki2` are supported, newer
+    /// Anki versions can export them with "Support older Anki versions" option.
+    UnsupportedFormat,
+    InvalidCollection,
+    /// Decompressed contents are over their size limit.
+    TooLarge,
+    /// Package cannot be written.
+    WriteFailed,
+}
+
+api_error!(AnkiError {
+    Self::InvalidArchive => (BAD_REQUEST, "invalid_archive", "Package is not a zip archive."),
+    Self::UnsupportedFormat => (
+        BAD_REQUEST,
+        "unsupported_format",
+        "Collection format is not supported."
+    ),
+    Self::InvalidCollection => (
+        BAD_REQUEST,
+        "invalid_collection",
+        "Collection is not a valid Anki collection."
+    ),
+    Self::TooLarge => (BAD_REQUEST, "too_large", "Package contents are too large."),
+    Self::WriteFailed => (INTERNAL_SERVER_ERROR, "write_failed", "Package cannot be written."),
+});
+
+impl From<rusqlite::Error> for AnkiError {
+    fn from(_: rusqlite::Error) -> Self {
+        Self::InvalidCollection
+    }
+}
+
+// Deck in `col.decks` of older collections.
+#[derive(Deserialize)]
+struct DeckJson {
+    name: String,
+}
+
+// Anki card types, queues of new and review cards are same as their types.
+const CARD_NEW: i64 = 0;
+const CARD_REVIEW: i64 = 2;
+const CARD_RELEARNING: i64 = 3;
+
+// Revlog types.
+const REVLOG_LEARN: i64 = 0;
+const REVLOG_REVIEW: i64 = 1;
+
+/// Note type of exported notes. A fixed id lets Anki reuse it in later imports.
+const MODEL_ID: i64 = 1_600_000_000_000;
+
+/// Deck to write into a package.
+#[derive(Debug)]
+pub struct AnkiExport {
+    pub deck_id: i64,
+    pub name: String,
+    pub description: String,
+    pub cards: Vec<AnkiExportCard>,
+}
+
+/// Card of an exported deck with its fields rendered as HTML.
+#[derive(Debug)]
+pub struct AnkiExportCard {
+    pub id: i64,
+    pub front: String,
+    pub back: String,
+    /// `None` for new cards.
+    pub review: Option<AnkiReview>,
+    pub revlog: Vec<AnkiRevlog>,
+}
+
+/// A graded review of a card.
+#[derive(Debug)]
+pub struct AnkiRevlog {
+    /// Review time in milliseconds since Unix epoch.
+    pub time: i64,
+    /// Anki answer button, between 1 and 4.
+    pub button: i64,
+    pub interval_days: f64,
+    pub last_interval_days: f64,
+    pub ease: f64,
+    pub elapsed_ms: i64,
+    /// Whether card was new before the review.
+    pub learning: bool,
+}
+
+impl AnkiPackage {
+    /// Reads package. SQLite can only open files, so the collection is written to `temp_path`
+    /// while it is read.
+    pub fn read(package: &[u8], temp_path: PathBuf) -> Result<Self, AnkiError> {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(package)).map_err(|_| AnkiError::InvalidArchive)?;
+
+        let name = ["collection.anki21", "collection.anki2"]
+            .into_iter()
+            .find(|name| archive.index_for_name(name).is_some())
+            .ok_or(AnkiError::UnsupportedFormat)?;
+        let entry = archive
+            .by_name(name)
+            .map_err(|_| AnkiError::InvalidArchive)?;
+        // declared size can be forged, reading is limited too
+        if entry.size() > COLLECTION_LIMIT {
+            return Err(AnkiError::TooLarge);
+        }
+        let mut collection = vec![];
+        entry
+            .take(COLLECTION_LIMIT + 1)
+            .read_to_end(&mut collection)
+            .map_err(|_| AnkiError::InvalidArchive)?;
+        if collection.len() as u64 > COLLECTION_LIMIT {
+            return Err(AnkiError::TooLarge);
+        }
+
+        let media = match archive.by_name("media") {
+            Ok(media) if media.size() > MEDIA_LIMIT => return Err(AnkiError::TooLarge),
+            Ok(media) => {
+                serde_json::from_reader::<_, BTreeMap<String, String>>(media.take(MEDIA_LIMIT))
+                    .map_or(0, |media| media.len())
+            }
+            Err(_) => 0,
+        };
+
+        let file = TempFile(temp_path);
+        std::fs::write(&file.0, collection).map_err(|_| AnkiError::InvalidCollection)?;
+        let connection = Connection::open_with_flags(&file.0, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let mut package = Self::from_collection(&connection)?;
+        package.media = media;
+        Ok(package)
+    }
+
+    fn from_collection(connection: &Connection) -> Result<Self, AnkiError> {
+        let (created, decks) = connection.query_row("SELECT crt, decks FROM col", [], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut decks = serde_json::from_str::<BTreeMap<String, DeckJson>>(&decks)
+            .map_err(|_| AnkiError::InvalidCollection)?
+            .into_iter()
+            .filter_map(|(id, deck)| {
+                Some(AnkiDeck {
+                    id: id.parse().ok()?,
+                    name: deck.name,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // decks have their own table since schema 18, names are separated by 0x1f
+        if decks.is_empty() {
+            decks = connection
+                .prepare("SELECT id, name FROM decks")?
+                .query_map([], |row| {
+                    Ok(AnkiDeck {
+                        id: row.get(0)?,
+                        name: row.get::<_, String>(1)?.replace('\x1f', "::"),
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+        }
+
+        let notes = connection
+            .prepare(
+                r#"
+                    SELECT
+                        notes.id, notes.flds, cards.did, cards.type, cards.due, cards.ivl,
+                        cards.factor, cards.reps, cards.lapses
+                    FROM notes
+                    JOIN cards ON cards.nid = notes.id
+                    WHERE cards.ord = (SELECT MIN(ord) FROM cards WHERE nid = notes.id)
+                    ORDER BY notes.id
+                "#,
+            )?
+            .query_map([], |row| {
+                let kind: i64 = row.get(3)?;
+                let interval_days: i64 = row.get(5)?;
+                let repetitions: i32 = row.get(7)?;
+                let lapses: i32 = row.get(8)?;
+
+                // due of review cards is in days since collection creation
+                let review = ((kind == CARD_REVIEW || kind == CARD_RELEARNING)
+                    && interval_days > 0)
+                    .then(|| -> rusqlite::Result<_> {
+                        Ok(AnkiReview {
+                            due: created + row.get::<_, i64>(4)? * 86400,
+                            interval_days: interval_days as f64,
+                            ease: row.get::<_, i64>(6)? as f64 / 1000.0,
+                            repetitions: (repetitions - lapses).max(1),
+                            lapses,
+                        })
+                    })
+                    .transpose()?;
+
+                Ok(AnkiNote {
+                    id: row.get(0)?,
+                    deck_id: row.get(2)?,
+                    fields: row
+                        .get::<_, String>(1)?
+                        .split('\x1f')
+                        .map(plain_text)
+                        .collect(),
+                    review,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            decks,
+            notes,
+            media: 0,
+        })
+    }
+}
+
+impl AnkiExport {
+    /// Writes deck into an `.apkg` package with a `collection.anki2` collection. SQLite can only
+    /// write files, so the collection is written to `temp_path` first.
+    pub fn write(&self, now: i64, temp_path: PathBuf) -> Result<Vec<u8>, AnkiError> {
+        let file = TempFile(temp_path);
+        let _ = std::fs::remove_file(&file.0);
+
+        let connection = Connection::open(&file.0).map_err(|_| AnkiError::WriteFailed)?;
+        self.write_collection(&connection, now)
+            .map_err(|_| AnkiError::WriteFailed)?;
+        connection.close().map_err(|_| AnkiError::WriteFailed)?;
+        let collection = std::fs::read(&file.0).map_err(|_| AnkiError::WriteFailed)?;
+
+        let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        archive
+            .start_file("collection.anki2", options)
+            .and_then(|_| Ok(archive.write_all(&collection)?))
+            .and_then(|_| archive.start_file("media", options))
+            .and_then(|_| Ok(archive.write_all(b"{}")?))
+            .map_err(|_| AnkiError::WriteFailed)?;
+
+        archive
+            .finish()
+            .map(|archive| archive.into_inner())
+            .map_err(|_| AnkiError::WriteFailed)
+    }
+
+    fn write_collection(&self, connection: &Connection, now: i64) -> rusqlite::Result<()> {
+        connection.execute_batch(COLLECTION_SCHEMA)?;
+
+        // due days of review cards count from collection creation
+        let created = now - now.rem_euclid(86400);
+        let deck = json!({
+            "id": self.deck_id,
+            "name": self.name,
+            "desc": self.description,
+            "mod": now,
+            "usn": -1,
+            "conf": 1,
+            "dyn": 0,
+            "collapsed": false,
+            "browserCollapsed": false,
+            "extendNew": 0,
+            "extendRev": 0,
+            "newToday": [0, 0],
+            "revToday": [0, 0],
+            "lrnToday": [0, 0],
+            "timeToday": [0, 0],
+        });
+        let default_deck = json!({
+            "id": 1,
+            "name": "Default",
+            "desc": "",
+            "mod": now,
+            "usn": -1,
+            "conf": 1,
+            "dyn": 0,
+            "collapsed": false,
+            "browserCollapsed": false,
+            "extendNew": 0,
+            "extendRev": 0,
+            "newToday": [0, 0],
+            "revToday": [0, 0],
+            "lrnToday": [0, 0],
+            "timeToday": [0, 0],
+        });
+        let field = |name: &str, ord: i64| {
+            json!({
+                "name": name,
+                "ord": ord,
+                "sticky": false,
+                "rtl": false,
+                "font": "Arial",
+                "size": 20,
+                "media": [],
+            })
+        };
+        let model = json!({
+            "id": MODEL_ID,
+            "name": "Kino",
+            "type": 0,
+            "mod": now,
+            "usn": -1,
+            "sortf": 0,
+            "did": self.deck_id,
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": "{{Front}}",
+                "afmt": "{{FrontSide}}<hr id=answer>{{Back}}",
+                "bqfmt": "",
+                "bafmt": "",
+                "did": null,
+                "bfont": "",
+                "bsize": 0,
+            }],
+            "flds": [field("Front", 0), field("Back", 1)],
+            "css": ".card { font-family: arial; font-size: 20px; text-align: center; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "latexsvg": false,
+            "req": [[0, "any", [0]]],
+            "tags": [],
+            "vers": [],
+        });
+        let deck_config = json!({
+            "id": 1,
+            "name": "Default",
+            "mod": 0,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "bury": false,
+                "delays": [1.0, 10.0],
+                "initialFactor": 2500,
+                "ints": [1, 4, 0],
+                "order": 1,
+                "perDay": 20,
+            },
+            "lapse": {
+                "delays": [10.0],
+                "leechAction": 1,
+                "leechFails": 8,
+                "minInt": 1,
+                "mult": 0.0,
+            },
+            "rev": {
+                "bury": false,
+                "ease4": 1.3,
+                "ivlFct": 1.0,
+                "maxIvl": 36500,
+                "perDay": 200,
+                "hardFactor": 1.2,
+            },
+        });
+        let config = json!({
+            "nextPos": self.cards.len() + 1,
+            "estTimes": true,
+            "activeDecks": [1],
+            "sortType": "noteFld",
+            "timeLim": 0,
+            "sortBackwards": false,
+            "addToCur": true,
+            "curDeck": 1,
+            "newSpread": 0,
+            "dueCounts": true,
+            "curModel": MODEL_ID,
+            "collapseTime": 1200,
+        });
+
+        connection.execute(
+            r#"
+                INSERT INTO col
+                VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')
+            "#,
+            params![
+                created,
+                now * 1000,
+                now * 1000,
+                config.to_string(),
+                json!({ MODEL_ID.to_string(): model }).to_string(),
+                json!({ "1": default_deck, self.deck_id.to_string(): deck }).to_string(),
+                json!({ "1": deck_config }).to_string(),
+            ],
+        )?;
+
+        let mut revlog_id = 0;
+        for (position, card) in self.cards.iter().enumerate() {
+            let sort_field = plain_text(&card.front);
+            let checksum = sha1_smol::Sha1::from(&sort_field).digest().bytes();
+            connection.execute(
+                r#"
+                    INSERT INTO notes
+                    VALUES (?1, ?2, ?3, ?4, -1, '', ?5, ?6, ?7, 0, '')
+                "#,
+                params![
+                    card.id,
+                    format!("kino{}", card.id),
+                    MODEL_ID,
+                    now,
+                    format!("{}\x1f{}", card.front, card.back),
+                    sort_field,
+                    u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]),
+                ],
+            )?;
+
+            let (kind, due, interval, ease, repetitions, lapses) = match card.review {
+                Some(review) => (
+                    CARD_REVIEW,
+                    (review.due - created).div_euclid(86400),
+                    (review.interval_days.round() as i64).max(1),
+                    (review.ease * 1000.0) as i64,
+                    review.repetitions,
+                    review.lapses,
+                ),
+                None => (CARD_NEW, position as i64 + 1, 0, 0, 0, 0),
+            };
+            connection.execute(
+                r#"
+                    INSERT INTO cards
+                    VALUES (?1, ?1, ?2, 0, ?3, -1, ?4, ?4, ?5, ?6, ?7, ?8, ?9, 0, 0, 0, 0, '')
+                "#,
+                params![
+                    card.id,
+                    self.deck_id,
+                    now,
+                    kind,
+                    due,
+                    interval,
+                    ease,
+                    repetitions,
+                    lapses
+                ],
+            )?;
+
+            for review in &card.revlog {
+                // revlog ids are review times, they must be unique
+                revlog_id = review.time.max(revlog_id + 1);
+                connection.execute(
+                    "INSERT INTO revlog VALUES (?1, ?2, -1, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        revlog_id,
+                        card.id,
+                        review.button,
+                        anki_interval(review.interval_days),
+                        anki_interval(review.last_interval_days),
+                        (review.ease * 1000.0) as i64,
+                        review.elapsed_ms,
+                        if review.learning {
+                            REVLOG_LEARN
+                        } else {
+                            REVLOG_REVIEW
+                        },
+                    ],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Schema 11 collection, which every Anki version can import.
+const COLLECTION_SCHEMA: &str = r#"
+    CREATE TABLE col (
+        id integer PRIMARY KEY, crt integer NOT NULL, mod integer NOT NULL,
+        scm integer NOT NULL, ver integer NOT NULL, dty integer NOT NULL, usn integer NOT NULL,
+        ls integer NOT NULL, conf text NOT NULL, models text NOT NULL, decks text NOT NULL,
+        dconf text NOT NULL, tags text NOT NULL
+    );
+    CREATE TABLE notes (
+        id integer PRIMARY KEY, guid text NOT NULL, mid integer NOT NULL, mod integer NOT NULL,
+        usn integer NOT NULL, tags text NOT NULL, flds text NOT NULL, sfld integer NOT NULL,
+        csum integer NOT NULL, flags integer NOT NULL, data text NOT NULL
+    );
+    CREATE TABLE cards (
+        id integer PRIMARY KEY, nid integer NOT NULL, did integer NOT NULL, ord integer NOT NULL,
+        mod integer NOT NULL, usn integer NOT NULL, type integer NOT NULL, queue integer NOT NULL,
+        due integer NOT NULL, ivl integer NOT NULL, factor integer NOT NULL, reps integer NOT NULL,
+        lapses integer NOT NULL, left integer NOT NULL, odue integer NOT NULL,
+        odid integer NOT NULL, flags integer NOT NULL, data text NOT NULL
+    );
+    CREATE TABLE revlog (
+        id integer PRIMARY KEY, cid integer NOT NULL, usn integer NOT NULL, ease integer NOT NULL,
+        ivl integer NOT NULL, lastIvl integer NOT NULL, factor integer NOT NULL,
+        time integer NOT NULL, type integer NOT NULL
+    );
+    CREATE TABLE graves (usn integer NOT NULL, oid integer NOT NULL, type integer NOT NULL);
+    CREATE INDEX ix_notes_usn ON notes (usn);
+    CREATE INDEX ix_cards_usn ON cards (usn);
+    CREATE INDEX ix_revlog_usn ON revlog (usn);
+    CREATE INDEX ix_cards_nid ON cards (nid);
+    CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+    CREATE INDEX ix_revlog_cid ON revlog (cid);
+    CREATE INDEX ix_notes_csum ON notes (csum);
+"#;
+
+// Anki intervals are in days, or in negative seconds if shorter than a day.
+fn anki_interval(days: f64) -> i64 {
+    if days >= 1.0 {
+        days.round() as i64
+    } else {
+        -(days * 86400.0).round() as i64
+    }
+}
+
+/// Renders a face as HTML field. WordNet faces are rendered with their definitions, so that
+/// they can be read without the extension.
+pub fn render_face(
+    wordnet: &WordNetDatabase,
+    extension_id: Option<i64>,
+    data: Option<&str>,
+) -> String {
+    let Some(data) = data else {
+        return String::new();
+    };
+
+    if extension_id != Some(WORDNET_EXTENSION) {
+        return escape_html(data);
+    }
+
+    let mut html = format!("<b>{}</b><ol>", escape_html(data));
+    for id in wordnet.synsets(data) {
+        let Some(glossary) = wordnet.synset(id) else {
+            continue;
+        };
+        html += &format!(
+            "<li><i>{}</i> {}",
+            WordNetDatabase::WORD_TYPES[id.pos],
+            escape_html(glossary.meanings())
+        );
+        for example in glossary.examples() {
+            html += &format!("<br><i>{}</i>", escape_html(example));
+        }
+        html += "</li>";
+    }
+    html += "</ol>";
+    html
+}
+
+/// Built-in WordNet extension.
+const WORDNET_EXTENSION: i64 = 2;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Removes the file when dropped.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Converts a field to plain text, removing HTML tags and media references.
+fn plain_text(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(start) = rest.find(['<', '[']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = if rest.starts_with('<') {
+            rest.find('>')
+        } else if rest.starts_with("[sound:") {
+            rest.find(']')
+        } else {
+            text.push('[');
+            rest = &rest[1..];
+            continue;
+        };
+        match end {
+            Some(end) => {
+                // line breaks and block ends separate words
+                if rest.starts_with("<br") || rest.starts_with("</div") || rest.starts_with("</p") {
+                    text.push(' ');
+                }
+                rest = &rest[end + 1..];
+            }
+            None => rest = "",
+        }
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_removes_tags_and_media() {
+        assert_eq!(
+            plain_text("<b>apple</b>[sound:apple.mp3]<br>a <i>fruit</i>"),
+            "apple a fruit"
+        );
+        assert_eq!(plain_text("<div>one</div><div>two</div>"), "one two");
+        assert_eq!(plain_text("a<img src=\"x.jpg\">b"), "ab");
+    }
+
+    #[test]
+    fn plain_text_keeps_brackets_and_decodes_entities() {
+        assert_eq!(plain_text("[noun] fish &amp; chips"), "[noun] fish & chips");
+        assert_eq!(plain_text("&lt;tag&gt;&nbsp;&quot;x&#39;"), "<tag> \"x'");
+        assert_eq!(plain_text("a &lt;br&gt; b"), "a <br> b");
+    }
+
+    #[test]
+    fn plain_text_drops_unclosed_tags() {
+        assert_eq!(plain_text("apple <b"), "apple");
+        assert_eq!(plain_text("apple [sound:a.mp3"), "apple");
+    }
+
+    #[test]
+    fn anki_interval_units() {
+        assert_eq!(anki_interval(1.0), 1);
+        assert_eq!(anki_interval(6.4), 6);
+        assert_eq!(anki_interval(15.5), 16);
+        // shorter than a day in negative seconds
+        assert_eq!(anki_interval(10.0 / (24.0 * 60.0)), -600);
+        assert_eq!(anki_interval(0.0), 0);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::dicts::{Bundle, WordNetDatabase};
+
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use axum::body::Bytes;
+
+use tokio::sync::{Mutex, OnceCell};
+
+/// Encoded dictionary bundles for offline clients, cached in memory.
+///
+/// Full bundles are written to `directory` as `<version>.bundle`, so that clients of this
+/// version can receive deltas after dictionary changes.
+pub struct BundleStore {
+    wordnet: Arc<WordNetDatabase>,
+    directory: Option<PathBuf>,
+    full: OnceCell<Bytes>,
+    deltas: Mutex<BTreeMap<String, Bytes>>,
+}
+
+impl BundleStore {
+    /// Creates a [`BundleStore`] for dictionary.
+    pub fn new(wordnet: Arc<WordNetDatabase>, directory: Option<PathBuf>) -> Self {
+        Self {
+            wordnet,
+            directory,
+            full: OnceCell::new(),
+            deltas: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Full bundle of the loaded dictionary.
+    pub async fn full(&self) -> Bytes {
+        self.full
+            .get_or_init(|| async {
+                let wordnet = Arc::clone(&self.wordnet);
+                let bundle = tokio::task::spawn_blocking(move || wordnet.bundle().encode())
+                    .await
+                    .unwrap();
+
+                if let Some(path) = self.path(self.wordnet.version()) {
+                    if tokio::fs::write(&path, &bundle).await.is_err() {
+                        tracing::warn!("Cannot save dictionary bundle to {path:?}");
+                    }
+                }
+
+                Bytes::from(bundle)
+            })
+            .await
+            .clone()
+    }
+
+    /// Delta bundle from version `base` to the loaded dictionary. Returns `None` if full bundle
+    /// of `base` is not available.
+    pub async fn delta(&self, base: &str) -> Option<Bytes> {
+        let path = self.path(base)?;
+
+        // deltas are built once, lock is held to avoid building same delta concurrently
+        let mut deltas = self.deltas.lock().await;
+        if let Some(delta) = deltas.get(base) {
+            return Some(delta.clone());
+        }
+
+        let old = tokio::fs::read(path).await.ok()?;
+        let wordnet = Arc::clone(&self.wordnet);
+        let delta = tokio::task::spawn_blocking(move || {
+            let old = Bundle::decode(&old).ok()?;
+            Some(Bundle::delta(&old, &wordnet.bundle()).encode())
+        })
+        .await
+        .ok()??;
+
+        let delta = Bytes::from(delta);
+        deltas.insert(base.to_string(), delta.clone());
+        Some(delta)
+    }
+
+    // Versions are hex hashes, anything else is rejected to prevent path traversal.
+    fn path(&self, version: &str) -> Option<PathBuf> {
+        if version.is_empty() || !version.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let mut path = self.directory.clone()?;
+        path.push(format!("{version}.bundle"));
+        Some(path)
+    }
+}
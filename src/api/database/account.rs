@@ -0,0 +1,524 @@
+use super::{Card, Deck, Extension, Face, Orm, Review};
+
+use crate::api::{
+    error::api_error,
+    scheduler::{ReviewGrade, Scheduler},
+};
+
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+/// Version of [`AccountArchive`] format.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub email: String,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+}
+
+/// All data of a user. Extensions are only the ones that user created.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountArchive {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub profile: Profile,
+    pub decks: Vec<Deck>,
+    pub cards: Vec<Card>,
+    pub faces: Vec<Face>,
+    pub extensions: Vec<Extension>,
+    pub reviews: Vec<Review>,
+}
+
+/// Numbers of restored rows.
+#[derive(Debug, Serialize)]
+pub struct AccountImportReport {
+    pub decks: usize,
+    pub cards: usize,
+    pub faces: usize,
+    pub extensions: usize,
+    pub reviews: usize,
+}
+
+/// Account import error.
+#[derive(Debug)]
+pub enum ArchiveError {
+    UnsupportedVersion,
+    /// Archives are only restored into accounts without cards.
+    AccountNotEmpty,
+    /// A row refers to a row that is not in the archive.
+    InvalidReference,
+    Database(sqlx::Error),
+}
+
+api_error!(ArchiveError {
+    Self::UnsupportedVersion => (
+        BAD_REQUEST,
+        "unsupported_version",
+        "Archive version is not supported."
+    ),
+    Self::AccountNotEmpty => (CONFLICT, "account_not_empty", "Account already has cards."),
+    Self::InvalidReference => (
+        BAD_REQUEST,
+        "invalid_reference",
+        "Archive refers to missing data."
+    ),
+    Self::Database(error) => (INTERNAL_SERVER_ERROR, "database", error),
+});
+
+impl From<sqlx::Error> for ArchiveError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+// Maps ids of an archive to new ids.
+struct IdMap(HashMap<i64, i64>);
+
+impl IdMap {
+    fn new(ids: impl Iterator<Item = i64>, orm: &Orm) -> Self {
+        Self(ids.map(|id| (id, orm.snowflake.gen_id())).collect())
+    }
+
+    fn get(&self, id: i64) -> Result<i64, ArchiveError> {
+        self.0
+            .get(&id)
+            .copied()
+            .ok_or(ArchiveError::InvalidReference)
+    }
+}
+
+impl Orm {
+    /// Collects all data of user.
+    pub async fn export_account(&self, user_id: i64) -> Option<AccountArchive> {
+        // a snapshot keeps references between rows consistent with concurrent changes
+        let mut tx = self.db.begin().await.ok()?;
+        sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+
+        let profile = sqlx::query_as!(
+            Profile,
+            "SELECT email, username, name, picture FROM users WHERE id = $1",
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .ok()?;
+
+        let decks = sqlx::query_as!(
+            Deck,
+            r#"
+                SELECT
+                    id, owner_id, card_count, interval, level,
+                    scheduler AS "scheduler: Scheduler", name, description, position
+                FROM decks
+                WHERE owner_id = $1
+                ORDER BY position, id
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .ok()?;
+
+        let cards = sqlx::query_as!(
+            Card,
+            "SELECT * FROM cards WHERE owner_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .ok()?;
+
+        let faces = sqlx::query_as!(
+            Face,
+            "SELECT * FROM faces WHERE owner_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .ok()?;
+
+        let extensions = sqlx::query_as!(
+            Extension,
+            "SELECT * FROM extensions WHERE owner_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .ok()?;
+
+        let reviews = sqlx::query_as!(
+            Review,
+            r#"
+                SELECT
+                    id, card_id, owner_id, reviewed_at, grade AS "grade: ReviewGrade",
+                    elapsed_ms, previous_interval_days, next_interval_days, previous_deck_id,
+                    previous_done_at, previous_due_at, previous_ease, previous_repetitions,
+                    previous_lapses
+                FROM reviews
+                WHERE owner_id = $1
+                ORDER BY reviewed_at, id
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .ok()?;
+
+        let exported_at = sqlx::query_scalar!(r#"SELECT LOCALTIMESTAMP AS "now!""#)
+            .fetch_one(&mut *tx)
+            .await
+            .ok()?;
+
+        tx.commit().await.ok()?;
+
+        Some(AccountArchive {
+            version: ARCHIVE_VERSION,
+            exported_at,
+            profile,
+            decks,
+            cards,
+            faces,
+            extensions,
+            reviews,
+        })
+    }
+
+    /// Restores an archive into an account without cards, replacing its decks. Every row gets
+    /// a new id. Profile name and picture are restored, username and email are kept.
+    pub async fn import_account(
+        &self,
+        archive: AccountArchive,
+        user_id: i64,
+    ) -> Result<AccountImportReport, ArchiveError> {
+        if archive.version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion);
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        let has_cards = sqlx::query_scalar!(
+            r#"
+                SELECT
+                    EXISTS(SELECT 1 FROM cards WHERE owner_id = $1) OR
+                    EXISTS(SELECT 1 FROM faces WHERE owner_id = $1) OR
+                    EXISTS(SELECT 1 FROM extensions WHERE owner_id = $1) AS "exists!"
+                FROM users
+                WHERE id = $1
+                FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if has_cards {
+            return Err(ArchiveError::AccountNotEmpty);
+        }
+
+        sqlx::query!("DELETE FROM decks WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "UPDATE users SET name = $2, picture = $3 WHERE id = $1",
+            user_id,
+            archive.profile.name,
+            archive.profile.picture
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let extension_ids = IdMap::new(archive.extensions.iter().map(|e| e.id), self);
+        let deck_ids = IdMap::new(archive.decks.iter().map(|deck| deck.id), self);
+        let face_ids = IdMap::new(archive.faces.iter().map(|face| face.id), self);
+        let card_ids = IdMap::new(archive.cards.iter().map(|card| card.id), self);
+
+        sqlx::query!(
+            r#"
+                INSERT INTO extensions (id, owner_id, name, data)
+                SELECT id, $4, name, data
+                FROM unnest($1::bigint[], $2::varchar[], $3::varchar[]) AS e(id, name, data)
+            "#,
+            &archive
+                .extensions
+                .iter()
+                .map(|extension| extension_ids.get(extension.id))
+                .collect::<Result<Vec<_>, _>>()?,
+            &archive
+                .extensions
+                .iter()
+                .map(|extension| extension.name.clone())
+                .collect::<Vec<_>>(),
+            &archive
+                .extensions
+                .iter()
+                .map(|extension| extension.data.clone())
+                .collect::<Vec<_>>(),
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // card counts are maintained by triggers while cards are inserted
+        let (mut ids, mut intervals, mut levels, mut schedulers) = (vec![], vec![], vec![], vec![]);
+        let (mut names, mut descriptions, mut positions) = (vec![], vec![], vec![]);
+        for deck in &archive.decks {
+            ids.push(deck_ids.get(deck.id)?);
+            intervals.push(deck.interval);
+            levels.push(deck.level);
+            schedulers.push(deck.scheduler);
+            names.push(deck.name.clone());
+            descriptions.push(deck.description.clone());
+            positions.push(deck.position);
+        }
+        sqlx::query!(
+            r#"
+                INSERT INTO decks (
+                    id, owner_id, card_count, interval, level, scheduler, name, description,
+                    position
+                )
+                SELECT id, $8, 0, interval, level, scheduler, name, description, position
+                FROM unnest(
+                    $1::bigint[], $2::interval[], $3::int[], $4::scheduler[], $5::varchar[],
+                    $6::varchar[], $7::int[]
+                ) AS d(id, interval, level, scheduler, name, description, position)
+            "#,
+            &ids,
+            &intervals,
+            &levels,
+            &schedulers as &[Scheduler],
+            &names,
+            &descriptions as &[Option<String>],
+            &positions,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // faces of built-in extensions keep their extension ids
+        let mut face_extensions = Vec::with_capacity(archive.faces.len());
+        let mut built_in = vec![];
+        for face in &archive.faces {
+            let id = face.extension_id.ok_or(ArchiveError::InvalidReference)?;
+            face_extensions.push(extension_ids.get(id).unwrap_or_else(|_| {
+                built_in.push(id);
+                id
+            }));
+        }
+        built_in.sort_unstable();
+        built_in.dedup();
+        let found = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!" FROM extensions
+                WHERE id = ANY($1) AND owner_id IS NULL
+            "#,
+            &built_in
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if found != built_in.len() as i64 {
+            return Err(ArchiveError::InvalidReference);
+        }
+        sqlx::query!(
+            r#"
+                INSERT INTO faces (id, owner_id, extension_id, data)
+                SELECT id, $4, extension_id, data
+                FROM unnest($1::bigint[], $2::bigint[], $3::varchar[]) AS f(id, extension_id, data)
+            "#,
+            &archive
+                .faces
+                .iter()
+                .map(|face| face_ids.get(face.id))
+                .collect::<Result<Vec<_>, _>>()?,
+            &face_extensions,
+            &archive
+                .faces
+                .iter()
+                .map(|face| face.data.clone())
+                .collect::<Vec<_>>() as &[Option<String>],
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // back faces are passed flat with their card ids and gathered in order
+        let (mut ids, mut decks, mut fronts) = (vec![], vec![], vec![]);
+        let (mut done_at, mut due_at, mut eases) = (vec![], vec![], vec![]);
+        let (mut interval_days, mut repetitions, mut lapses) = (vec![], vec![], vec![]);
+        let (mut back_cards, mut backs) = (vec![], vec![]);
+        for card in &archive.cards {
+            let id = card_ids.get(card.id)?;
+            ids.push(id);
+            decks.push(deck_ids.get(card.deck_id)?);
+            fronts.push(face_ids.get(card.front)?);
+            for &back in &card.back {
+                back_cards.push(id);
+                backs.push(face_ids.get(back)?);
+            }
+            done_at.push(card.done_at);
+            due_at.push(card.due_at);
+            eases.push(card.ease);
+            interval_days.push(card.interval_days);
+            repetitions.push(card.repetitions);
+            lapses.push(card.lapses);
+        }
+        sqlx::query!(
+            r#"
+                INSERT INTO cards (
+                    id, owner_id, deck_id, front, back, done_at, due_at, ease, interval_days,
+                    repetitions, lapses
+                )
+                SELECT
+                    c.id, $12, c.deck_id, c.front,
+                    ARRAY(
+                        SELECT b.face_id
+                        FROM unnest($10::bigint[], $11::bigint[]) WITH ORDINALITY AS b(card_id, face_id, ord)
+                        WHERE b.card_id = c.id
+                        ORDER BY b.ord
+                    ),
+                    c.done_at, c.due_at, c.ease, c.interval_days, c.repetitions, c.lapses
+                FROM unnest(
+                    $1::bigint[], $2::bigint[], $3::bigint[], $4::timestamp[], $5::timestamp[],
+                    $6::float8[], $7::float8[], $8::int[], $9::int[]
+                ) AS c(
+                    id, deck_id, front, done_at, due_at, ease, interval_days, repetitions, lapses
+                )
+            "#,
+            &ids,
+            &decks,
+            &fronts,
+            &done_at as &[Option<NaiveDateTime>],
+            &due_at as &[Option<NaiveDateTime>],
+            &eases,
+            &interval_days,
+            &repetitions,
+            &lapses,
+            &back_cards,
+            &backs,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // reviews of since deleted decks are kept under the deck of their card
+        let card_decks: HashMap<i64, i64> = archive
+            .cards
+            .iter()
+            .map(|card| (card.id, card.deck_id))
+            .collect();
+        let (mut ids, mut cards, mut previous_decks) = (vec![], vec![], vec![]);
+        for review in &archive.reviews {
+            ids.push(self.snowflake.gen_id());
+            cards.push(card_ids.get(review.card_id)?);
+            let previous_deck = match deck_ids.get(review.previous_deck_id) {
+                Ok(id) => id,
+                Err(_) => deck_ids.get(card_decks[&review.card_id])?,
+            };
+            previous_decks.push(previous_deck);
+        }
+        macro_rules! column {
+            ($field:ident) => {
+                archive
+                    .reviews
+                    .iter()
+                    .map(|review| review.$field)
+                    .collect::<Vec<_>>()
+            };
+        }
+        sqlx::query!(
+            r#"
+                INSERT INTO reviews (
+                    id, card_id, owner_id, reviewed_at, grade, elapsed_ms,
+                    previous_interval_days, next_interval_days, previous_deck_id,
+                    previous_done_at, previous_due_at, previous_ease, previous_repetitions,
+                    previous_lapses
+                )
+                SELECT
+                    r.id, r.card_id, $14, r.reviewed_at, r.grade, r.elapsed_ms,
+                    r.previous_interval_days, r.next_interval_days, r.previous_deck_id,
+                    r.previous_done_at, r.previous_due_at, r.previous_ease,
+                    r.previous_repetitions, r.previous_lapses
+                FROM unnest(
+                    $1::bigint[], $2::bigint[], $3::timestamp[], $4::review_grade[], $5::int[],
+                    $6::float8[], $7::float8[], $8::bigint[], $9::timestamp[], $10::timestamp[],
+                    $11::float8[], $12::int[], $13::int[]
+                ) AS r(
+                    id, card_id, reviewed_at, grade, elapsed_ms, previous_interval_days,
+                    next_interval_days, previous_deck_id, previous_done_at, previous_due_at,
+                    previous_ease, previous_repetitions, previous_lapses
+                )
+            "#,
+            &ids,
+            &cards,
+            &column!(reviewed_at),
+            &column!(grade) as &[Option<ReviewGrade>],
+            &column!(elapsed_ms) as &[Option<i32>],
+            &column!(previous_interval_days),
+            &column!(next_interval_days),
+            &previous_decks,
+            &column!(previous_done_at) as &[Option<NaiveDateTime>],
+            &column!(previous_due_at) as &[Option<NaiveDateTime>],
+            &column!(previous_ease),
+            &column!(previous_repetitions),
+            &column!(previous_lapses),
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(AccountImportReport {
+            decks: archive.decks.len(),
+            cards: archive.cards.len(),
+            faces: archive.faces.len(),
+            extensions: archive.extensions.len(),
+            reviews: archive.reviews.len(),
+        })
+    }
+
+    /// Removes user and all data of user.
+    pub async fn delete_account(&self, user_id: i64) -> Option<()> {
+        let mut tx = self.db.begin().await.ok()?;
+
+        // rows go before the rows they refer to
+        sqlx::query!("DELETE FROM reviews WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        sqlx::query!("DELETE FROM cards WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        sqlx::query!("DELETE FROM faces WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        sqlx::query!("DELETE FROM decks WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        sqlx::query!("DELETE FROM extensions WHERE owner_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        let deleted = sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?
+            .rows_affected();
+        if deleted == 0 {
+            return None;
+        }
+
+        tx.commit().await.ok()
+    }
+}
@@ -0,0 +1,461 @@
+use super::Orm;
+
+use crate::api::error::api_error;
+
+use serde::{Deserialize, Serialize};
+
+use sqlx::{Postgres, Transaction};
+
+/// Maximum length of face data in characters, as in `faces.data` column.
+pub const MAX_FACE_DATA: usize = 128;
+
+/// Maximum number of cards in a bulk creation request.
+pub const BULK_LIMIT: usize = 512;
+
+#[derive(Deserialize)]
+pub struct CreateCard {
+    pub deck_id: i64,
+    pub front: (i64, Option<String>),
+    pub back: Vec<(i64, Option<String>)>,
+}
+
+#[derive(Serialize)]
+pub struct CreateCardResponse {
+    pub card_id: i64,
+    pub front: i64,
+    pub back: Vec<i64>,
+}
+
+/// Result of a card in bulk creation.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BulkCardResult {
+    Created(CreateCardResponse),
+    Failed { error: &'static str },
+}
+
+impl From<Result<CreateCardResponse, CardError>> for BulkCardResult {
+    fn from(result: Result<CreateCardResponse, CardError>) -> Self {
+        match result {
+            Ok(card) => Self::Created(card),
+            Err(error) => Self::Failed {
+                error: error.code(),
+            },
+        }
+    }
+}
+
+/// New state of card's faces, missing fields are kept.
+#[derive(Deserialize)]
+pub struct EditCard {
+    pub front: Option<(i64, Option<String>)>,
+    /// Back faces in their new order. Faces of the card that are not listed are deleted.
+    pub back: Option<Vec<EditFace>>,
+}
+
+#[derive(Deserialize)]
+pub struct EditFace {
+    /// An existing back face of the card, or `None` to add a face.
+    pub id: Option<i64>,
+    pub extension_id: i64,
+    pub data: Option<String>,
+}
+
+/// Card creation and editing error.
+#[derive(Debug)]
+pub enum CardError {
+    DeckNotFound,
+    CardNotFound,
+    EmptyBack,
+    FaceTooLong,
+    ExtensionNotFound,
+    /// Face id is not a back face of the card or is listed twice.
+    InvalidFace,
+    Database(sqlx::Error),
+}
+
+api_error!(CardError {
+    Self::DeckNotFound => (NOT_FOUND, "deck_not_found", "Deck is not found."),
+    Self::CardNotFound => (NOT_FOUND, "card_not_found", "Card is not found."),
+    Self::EmptyBack => (BAD_REQUEST, "empty_back", "Card has no back face."),
+    Self::FaceTooLong => (
+        BAD_REQUEST,
+        "face_too_long",
+        format_args!("Face data is longer than {MAX_FACE_DATA} characters.")
+    ),
+    Self::ExtensionNotFound => (BAD_REQUEST, "extension_not_found", "Extension is not found."),
+    Self::InvalidFace => (BAD_REQUEST, "invalid_face", "Face is not a back face of the card."),
+    Self::Database(error) => (INTERNAL_SERVER_ERROR, "database", error),
+});
+
+impl From<sqlx::Error> for CardError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl CreateCard {
+    fn validate(&self) -> Result<(), CardError> {
+        if self.back.is_empty() {
+            return Err(CardError::EmptyBack);
+        }
+        validate_faces(
+            [&self.front]
+                .into_iter()
+                .chain(&self.back)
+                .map(|(_, data)| data),
+        )
+    }
+
+    fn extension_ids(&self) -> Vec<i64> {
+        extension_ids(
+            [&self.front]
+                .into_iter()
+                .chain(&self.back)
+                .map(|(extension_id, _)| *extension_id),
+        )
+    }
+}
+
+impl EditCard {
+    fn validate(&self) -> Result<(), CardError> {
+        if self.back.as_ref().is_some_and(|back| back.is_empty()) {
+            return Err(CardError::EmptyBack);
+        }
+        validate_faces(
+            self.front
+                .iter()
+                .map(|(_, data)| data)
+                .chain(self.back.iter().flatten().map(|face| &face.data)),
+        )
+    }
+
+    fn extension_ids(&self) -> Vec<i64> {
+        extension_ids(
+            self.front
+                .iter()
+                .map(|(extension_id, _)| *extension_id)
+                .chain(self.back.iter().flatten().map(|face| face.extension_id)),
+        )
+    }
+}
+
+fn validate_faces<'a>(mut data: impl Iterator<Item = &'a Option<String>>) -> Result<(), CardError> {
+    if data.any(|data| {
+        data.as_ref()
+            .is_some_and(|data| data.chars().count() > MAX_FACE_DATA)
+    }) {
+        return Err(CardError::FaceTooLong);
+    }
+    Ok(())
+}
+
+fn extension_ids(ids: impl Iterator<Item = i64>) -> Vec<i64> {
+    let mut ids = ids.collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+impl Orm {
+    /// Creates card with its faces.
+    pub async fn create_card(
+        &self,
+        card_options: CreateCard,
+        user_id: i64,
+    ) -> Result<CreateCardResponse, CardError> {
+        let mut results = self.create_cards(vec![card_options], user_id).await?;
+        // create_cards returns a result for each card
+        results.pop().expect("one result per card")
+    }
+
+    /// Creates valid cards in a single transaction. Results are in request order.
+    pub async fn create_cards(
+        &self,
+        cards: Vec<CreateCard>,
+        user_id: i64,
+    ) -> Result<Vec<Result<CreateCardResponse, CardError>>, CardError> {
+        let mut tx = self.db.begin().await?;
+        let results = self.insert_cards(&mut tx, cards, user_id).await?;
+        tx.commit().await?;
+        Ok(results)
+    }
+
+    // Creates valid cards in a transaction of caller.
+    pub(super) async fn insert_cards(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        cards: Vec<CreateCard>,
+        user_id: i64,
+    ) -> Result<Vec<Result<CreateCardResponse, CardError>>, CardError> {
+        let deck_ids = cards.iter().map(|card| card.deck_id).collect::<Vec<_>>();
+        let decks = sqlx::query_scalar!(
+            "SELECT id FROM decks WHERE id = ANY($1) AND owner_id = $2",
+            &deck_ids,
+            user_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let extension_ids = extension_ids(cards.iter().flat_map(|card| card.extension_ids()));
+        let extensions = sqlx::query_scalar!(
+            "SELECT id FROM extensions WHERE id = ANY($1) AND (owner_id IS NULL OR owner_id = $2)",
+            &extension_ids,
+            user_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let mut results = Vec::with_capacity(cards.len());
+        let (mut face_ids, mut face_extensions, mut face_data) = (vec![], vec![], vec![]);
+        let (mut card_ids, mut card_decks, mut fronts) = (vec![], vec![], vec![]);
+        let (mut back_cards, mut back_ids) = (vec![], vec![]);
+        for card in cards {
+            let valid = card.validate().and_then(|_| {
+                if !decks.contains(&card.deck_id) {
+                    return Err(CardError::DeckNotFound);
+                }
+                if !card
+                    .extension_ids()
+                    .iter()
+                    .all(|id| extensions.contains(id))
+                {
+                    return Err(CardError::ExtensionNotFound);
+                }
+                Ok(())
+            });
+            if let Err(error) = valid {
+                results.push(Err(error));
+                continue;
+            }
+
+            let card_id = self.snowflake.gen_id();
+            let front = self.snowflake.gen_id();
+            let back = card
+                .back
+                .iter()
+                .map(|_| self.snowflake.gen_id())
+                .collect::<Vec<_>>();
+
+            card_ids.push(card_id);
+            card_decks.push(card.deck_id);
+            fronts.push(front);
+            back_cards.extend(back.iter().map(|_| card_id));
+            back_ids.extend(&back);
+            face_ids.push(front);
+            face_ids.extend(&back);
+            for (extension_id, data) in [card.front].into_iter().chain(card.back) {
+                face_extensions.push(extension_id);
+                face_data.push(data);
+            }
+
+            results.push(Ok(CreateCardResponse {
+                card_id,
+                front,
+                back,
+            }));
+        }
+
+        Self::insert_faces(tx, &face_ids, &face_extensions, &face_data, user_id).await?;
+
+        // back faces are passed flat with their card ids and gathered in order
+        sqlx::query!(
+            r#"
+                INSERT INTO cards (id, owner_id, deck_id, front, back)
+                SELECT
+                    cards.id, $6, cards.deck_id, cards.front,
+                    array_agg(backs.face_id ORDER BY backs.ord)
+                FROM unnest($1::bigint[], $2::bigint[], $3::bigint[]) AS cards(id, deck_id, front)
+                JOIN unnest($4::bigint[], $5::bigint[]) WITH ORDINALITY AS backs(card_id, face_id, ord)
+                    ON backs.card_id = cards.id
+                GROUP BY cards.id, cards.deck_id, cards.front
+            "#,
+            &card_ids,
+            &card_decks,
+            &fronts,
+            &back_cards,
+            &back_ids,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(results)
+    }
+
+    /// Deletes card with its faces and reviews.
+    pub async fn delete_card(&self, card_id: i64, user_id: i64) -> Result<(), CardError> {
+        let mut tx = self.db.begin().await?;
+
+        let face_ids = sqlx::query_scalar!(
+            r#"
+                DELETE FROM cards
+                WHERE id = $1 AND owner_id = $2
+                RETURNING front || back AS "face_ids!"
+            "#,
+            card_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(CardError::CardNotFound)?;
+
+        sqlx::query!(
+            "DELETE FROM faces WHERE id = ANY($1) AND owner_id = $2",
+            &face_ids,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Changes faces of card, adding, removing and reordering its back faces.
+    pub async fn edit_card(
+        &self,
+        card_id: i64,
+        edit: EditCard,
+        user_id: i64,
+    ) -> Result<CreateCardResponse, CardError> {
+        edit.validate()?;
+
+        let mut tx = self.db.begin().await?;
+
+        let card = sqlx::query!(
+            "SELECT front, back FROM cards WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+            card_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(CardError::CardNotFound)?;
+
+        Self::check_extensions(&mut tx, &edit.extension_ids(), user_id).await?;
+
+        if let Some((extension_id, data)) = edit.front {
+            sqlx::query!(
+                "UPDATE faces SET extension_id = $3, data = $4 WHERE id = $1 AND owner_id = $2",
+                card.front,
+                user_id,
+                extension_id,
+                data
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let mut back_ids = card.back.clone();
+        if let Some(back) = edit.back {
+            back_ids = Vec::with_capacity(back.len());
+            for face in back {
+                let id = match face.id {
+                    Some(id) if card.back.contains(&id) && !back_ids.contains(&id) => {
+                        sqlx::query!(
+                            "UPDATE faces SET extension_id = $3, data = $4 WHERE id = $1 AND owner_id = $2",
+                            id,
+                            user_id,
+                            face.extension_id,
+                            face.data
+                        )
+                        .execute(&mut *tx)
+                        .await?;
+                        id
+                    }
+                    Some(_) => return Err(CardError::InvalidFace),
+                    None => {
+                        let id = self.snowflake.gen_id();
+                        Self::insert_faces(
+                            &mut tx,
+                            &[id],
+                            &[face.extension_id],
+                            &[face.data],
+                            user_id,
+                        )
+                        .await?;
+                        id
+                    }
+                };
+                back_ids.push(id);
+            }
+
+            let removed = card
+                .back
+                .iter()
+                .copied()
+                .filter(|id| !back_ids.contains(id))
+                .collect::<Vec<_>>();
+            sqlx::query!(
+                "DELETE FROM faces WHERE id = ANY($1) AND owner_id = $2",
+                &removed,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "UPDATE cards SET back = $2 WHERE id = $1",
+                card_id,
+                &back_ids
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(CreateCardResponse {
+            card_id,
+            front: card.front,
+            back: back_ids,
+        })
+    }
+
+    // Checks that extensions are either built-in or owned by user.
+    async fn check_extensions(
+        tx: &mut Transaction<'_, Postgres>,
+        ids: &[i64],
+        user_id: i64,
+    ) -> Result<(), CardError> {
+        let usable = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!" FROM extensions
+                WHERE id = ANY($1) AND (owner_id IS NULL OR owner_id = $2)
+            "#,
+            ids,
+            user_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        if usable != ids.len() as i64 {
+            return Err(CardError::ExtensionNotFound);
+        }
+        Ok(())
+    }
+
+    // Inserts faces of user in a single statement.
+    async fn insert_faces(
+        tx: &mut Transaction<'_, Postgres>,
+        ids: &[i64],
+        extension_ids: &[i64],
+        data: &[Option<String>],
+        user_id: i64,
+    ) -> Result<(), CardError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO faces (id, owner_id, extension_id, data)
+                SELECT id, $4, extension_id, data
+                FROM unnest($1::bigint[], $2::bigint[], $3::varchar[]) AS faces(id, extension_id, data)
+            "#,
+            ids,
+            extension_ids,
+            data as &[Option<String>],
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use super::{structs::PgIntervalRemote, Deck, Orm};
+
+use crate::api::scheduler::Scheduler;
+
+use std::borrow::Borrow;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use sqlx::{postgres::types::PgInterval, PgExecutor};
+
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckInterval(#[serde(with = "PgIntervalRemote")] pub PgInterval);
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateDeck {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[validate(length(max = 256))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_interval"))]
+    pub interval: DeckInterval,
+    #[validate(range(min = 0))]
+    #[serde(default)]
+    pub level: i32,
+    #[serde(default = "CreateDeck::default_scheduler")]
+    pub scheduler: Scheduler,
+}
+
+/// Deck fields to change, missing fields are kept.
+#[derive(Debug, Deserialize, Validate)]
+pub struct EditDeck {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    /// `Some(None)` clears description.
+    #[validate(length(max = 256))]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub description: Option<Option<String>>,
+    #[validate(custom(function = "validate_interval"))]
+    pub interval: Option<DeckInterval>,
+    #[validate(range(min = 0))]
+    pub level: Option<i32>,
+    pub scheduler: Option<Scheduler>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteDeck {
+    /// Deck to move cards into. Cards are deleted if not given.
+    pub move_to: Option<i64>,
+}
+
+impl CreateDeck {
+    fn default_scheduler() -> Scheduler {
+        Scheduler::Sm2
+    }
+}
+
+// Distinguishes a missing field from an explicit null.
+pub(super) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn validate_interval(interval: &DeckInterval) -> Result<(), ValidationError> {
+    let DeckInterval(interval) = interval;
+    if interval.months < 0 || interval.days < 0 || interval.microseconds < 0 {
+        return Err(ValidationError::new("negative_interval"));
+    }
+    Ok(())
+}
+
+impl Orm {
+    /// Creates a deck at the end of user's decks.
+    pub async fn create_deck(&self, deck: CreateDeck, user_id: i64) -> Option<Deck> {
+        self.insert_deck(self.db.borrow(), deck, user_id).await.ok()
+    }
+
+    // Creates deck with an executor of caller, so that it can be part of a transaction.
+    pub(super) async fn insert_deck(
+        &self,
+        executor: impl PgExecutor<'_>,
+        deck: CreateDeck,
+        user_id: i64,
+    ) -> Result<Deck, sqlx::Error> {
+        sqlx::query_as!(
+            Deck,
+            r#"
+                INSERT INTO decks (
+                    id, owner_id, card_count, interval, level, scheduler, name, description,
+                    position
+                )
+                SELECT
+                    $1, $2, 0, $3, $4, $5, $6, $7,
+                    COALESCE((SELECT MAX(position) + 1 FROM decks WHERE owner_id = $2), 0)
+                RETURNING
+                    id, owner_id, card_count, interval, level,
+                    scheduler AS "scheduler: Scheduler", name, description, position
+            "#,
+            self.snowflake.gen_id(),
+            user_id,
+            deck.interval.0,
+            deck.level,
+            deck.scheduler as Scheduler,
+            deck.name,
+            deck.description
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Changes name, description and scheduling options of deck.
+    pub async fn edit_deck(&self, deck_id: i64, deck: EditDeck, user_id: i64) -> Option<Deck> {
+        let (description_set, description) = match deck.description {
+            Some(description) => (true, description),
+            None => (false, None),
+        };
+
+        sqlx::query_as!(
+            Deck,
+            r#"
+                UPDATE decks
+                SET
+                    name = COALESCE($3, name),
+                    description = CASE WHEN $4 THEN $5 ELSE description END,
+                    interval = COALESCE($6, interval),
+                    level = COALESCE($7, level),
+                    scheduler = COALESCE($8, scheduler)
+                WHERE id = $1 AND owner_id = $2
+                RETURNING
+                    id, owner_id, card_count, interval, level,
+                    scheduler AS "scheduler: Scheduler", name, description, position
+            "#,
+            deck_id,
+            user_id,
+            deck.name,
+            description_set,
+            description,
+            deck.interval.map(|interval| interval.0),
+            deck.level,
+            deck.scheduler as Option<Scheduler>
+        )
+        .fetch_one(self.db.borrow())
+        .await
+        .ok()
+    }
+
+    /// Sets deck order to order of ids. Returns number of reordered decks.
+    pub async fn reorder_decks(&self, ids: &[i64], user_id: i64) -> u64 {
+        sqlx::query!(
+            r#"
+                UPDATE decks
+                SET position = ordered.position - 1
+                FROM unnest($1::bigint[]) WITH ORDINALITY AS ordered(id, position)
+                WHERE decks.id = ordered.id AND decks.owner_id = $2
+            "#,
+            ids,
+            user_id
+        )
+        .execute(self.db.borrow())
+        .await
+        .map(|result| result.rows_affected())
+        .unwrap_or(0)
+    }
+
+    /// Deletes deck, moving its cards to another deck or deleting them with their faces.
+    pub async fn delete_deck(&self, deck_id: i64, options: DeleteDeck, user_id: i64) -> Option<()> {
+        let mut tx = self.db.begin().await.ok()?;
+
+        sqlx::query_scalar!(
+            "SELECT 1 FROM decks WHERE id = $1 AND owner_id = $2 FOR UPDATE",
+            deck_id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .ok()?;
+
+        if let Some(move_to) = options.move_to {
+            if move_to == deck_id {
+                return None;
+            }
+
+            sqlx::query!(
+                r#"
+                    UPDATE cards SET deck_id = $3
+                    FROM decks
+                    WHERE cards.deck_id = $1 AND cards.owner_id = $2 AND
+                        decks.id = $3 AND decks.owner_id = $2
+                "#,
+                deck_id,
+                user_id,
+                move_to
+            )
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+
+            // target deck must exist even if there are no cards to move
+            sqlx::query_scalar!(
+                "SELECT 1 FROM decks WHERE id = $1 AND owner_id = $2",
+                move_to,
+                user_id
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .ok()?;
+        } else {
+            sqlx::query!(
+                r#"
+                    WITH deleted AS (
+                        DELETE FROM cards
+                        WHERE deck_id = $1 AND owner_id = $2
+                        RETURNING front || back AS face_ids
+                    )
+                    DELETE FROM faces
+                    WHERE
+                        id IN (SELECT unnest(face_ids) FROM deleted) AND
+                        owner_id = $2
+                "#,
+                deck_id,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+        }
+
+        sqlx::query!("DELETE FROM decks WHERE id = $1", deck_id)
+            .execute(&mut *tx)
+            .await
+            .ok()?;
+
+        tx.commit().await.ok()
+    }
+
+    /// Recomputes card counts of user's decks from cards. Returns number of corrected decks.
+    pub async fn recount_cards(&self, user_id: i64) -> u64 {
+        sqlx::query!(
+            r#"
+                UPDATE decks
+                SET card_count = counts.card_count
+                FROM (
+                    SELECT decks.id, COUNT(cards.id) AS card_count
+                    FROM decks
+                    LEFT JOIN cards ON cards.deck_id = decks.id
+                    WHERE decks.owner_id = $1
+                    GROUP BY decks.id
+                ) AS counts
+                WHERE decks.id = counts.id AND decks.card_count <> counts.card_count
+            "#,
+            user_id
+        )
+        .execute(self.db.borrow())
+        .await
+        .map(|result| result.rows_affected())
+        .unwrap_or(0)
+    }
+}
@@ -0,0 +1,125 @@
+use super::Orm;
+
+use crate::api::error::api_error;
+
+use std::borrow::Borrow;
+
+use chrono::NaiveDateTime;
+
+use serde::Serialize;
+
+/// Account at an OpenID Connect provider that user signs in with.
+#[derive(Debug, Serialize)]
+pub struct Identity {
+    pub id: i64,
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Identity linking error.
+#[derive(Debug)]
+pub enum IdentityError {
+    NotFound,
+    /// Identity already belongs to a user.
+    AlreadyLinked,
+    /// Users must keep an identity to sign in with.
+    LastIdentity,
+    Database(sqlx::Error),
+}
+
+api_error!(IdentityError {
+    Self::NotFound => (NOT_FOUND, "not_found", "Identity does not exist."),
+    Self::AlreadyLinked => (
+        CONFLICT,
+        "already_linked",
+        "Identity is already linked to a user."
+    ),
+    Self::LastIdentity => (
+        CONFLICT,
+        "last_identity",
+        "Last identity of user cannot be unlinked."
+    ),
+    Self::Database(error) => (INTERNAL_SERVER_ERROR, "database", error),
+});
+
+impl From<sqlx::Error> for IdentityError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl Orm {
+    /// Identities of user, oldest first.
+    pub async fn identities(&self, user_id: i64) -> Option<Vec<Identity>> {
+        sqlx::query_as!(
+            Identity,
+            r#"
+                SELECT id, issuer, subject, email, created_at
+                FROM identities
+                WHERE user_id = $1
+                ORDER BY created_at
+            "#,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()
+    }
+
+    /// Links an identity to user.
+    pub async fn link_identity(
+        &self,
+        user_id: i64,
+        issuer: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<Identity, IdentityError> {
+        sqlx::query_as!(
+            Identity,
+            r#"
+                INSERT INTO identities (id, user_id, issuer, subject, email, created_at)
+                VALUES ($1, $2, $3, $4, $5, LOCALTIMESTAMP)
+                ON CONFLICT (issuer, subject) DO NOTHING
+                RETURNING id, issuer, subject, email, created_at
+            "#,
+            self.snowflake.gen_id(),
+            user_id,
+            issuer,
+            subject,
+            email
+        )
+        .fetch_optional(self.db.borrow())
+        .await?
+        .ok_or(IdentityError::AlreadyLinked)
+    }
+
+    /// Unlinks an identity of user unless it is the last one.
+    pub async fn unlink_identity(&self, id: i64, user_id: i64) -> Result<(), IdentityError> {
+        let mut tx = self.db.begin().await?;
+
+        // locks identities of user, so that concurrent unlinks cannot remove all of them
+        let ids = sqlx::query_scalar!(
+            "SELECT id FROM identities WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !ids.contains(&id) {
+            return Err(IdentityError::NotFound);
+        }
+        if ids.len() == 1 {
+            return Err(IdentityError::LastIdentity);
+        }
+
+        sqlx::query!("DELETE FROM identities WHERE id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -1,15 +1,61 @@
 pub mod structs;
 
+/// Card reviews.
+mod review;
+
+/// Review session queue.
+mod queue;
+
+/// Learning statistics.
+mod stats;
+
+/// Deck management.
+mod decks;
+
+/// Card editing.
+mod cards;
+
+/// Deck import and export.
+mod transfer;
+
+/// Account data export and import.
+mod account;
+
+/// User profiles.
+mod profile;
+
+/// Refresh tokens.
+mod tokens;
+
+/// Sign in sessions.
+mod sessions;
+
+/// Linked sign in identities.
+mod identities;
+
 #[allow(unused_imports)]
 pub use structs::*;
 
+pub use account::AccountArchive;
+pub use cards::{BulkCardResult, CardError, CreateCard, EditCard, BULK_LIMIT};
+pub use decks::{CreateDeck, DeleteDeck, EditDeck};
+pub use profile::{EditProfile, ProfileError};
+pub use queue::QueueQuery;
+pub use review::{ReviewRequest, ReviewResponse};
+pub use stats::StatsQuery;
+pub use tokens::RefreshError;
+pub use transfer::{AnkiExportQuery, ExportQuery, ImportQuery, ImportRows};
+
 use std::{borrow::Borrow, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
 use sqlx::PgPool;
 
-use super::snowflake::Snowflake;
+use super::{
+    quiz::{QuizCard, WORD_EXTENSIONS},
+    snowflake::Snowflake,
+};
 
 #[derive(Clone)]
 pub struct Orm {
@@ -17,20 +63,6 @@ pub struct Orm {
     snowflake: Arc<Snowflake>,
 }
 
-#[derive(Deserialize)]
-pub struct CreateCard {
-    pub deck_id: i64,
-    pub front: (i64, Option<String>),
-    pub back: Vec<(i64, Option<String>)>,
-}
-
-#[derive(Serialize)]
-pub struct CreateCardResponse {
-    pub card_id: i64,
-    pub front: i64,
-    pub back: Vec<i64>,
-}
-
 impl Orm {
     /// Creates new Orm.
     pub fn new(db: Arc<PgPool>, snowflake: Arc<Snowflake>) -> Self {
@@ -41,14 +73,14 @@ impl Orm {
     pub async fn default_decks(&self, user_id: i64) -> bool {
         sqlx::query!(
             r#"
-                INSERT INTO decks 
+                INSERT INTO decks (id, owner_id, card_count, interval, level, scheduler, name, position)
                 VALUES
-                    ($2, $1, 0, INTERVAL '12 hours', 0),
-                    ($3, $1, 0, INTERVAL '1 day', 1),
-                    ($4, $1, 0, INTERVAL '2 days', 2),
-                    ($5, $1, 0, INTERVAL '4 days', 3),
-                    ($6, $1, 0, INTERVAL '9 days', 4),
-                    ($7, $1, 0, INTERVAL '14 days', 5)
+                    ($2, $1, 0, INTERVAL '12 hours', 0, 'leitner', 'Box 1', 0),
+                    ($3, $1, 0, INTERVAL '1 day', 1, 'leitner', 'Box 2', 1),
+                    ($4, $1, 0, INTERVAL '2 days', 2, 'leitner', 'Box 3', 2),
+                    ($5, $1, 0, INTERVAL '4 days', 3, 'leitner', 'Box 4', 3),
+                    ($6, $1, 0, INTERVAL '9 days', 4, 'leitner', 'Box 5', 4),
+                    ($7, $1, 0, INTERVAL '14 days', 5, 'leitner', 'Box 6', 5)
             "#,
             user_id,
             self.snowflake.gen_id(),
@@ -63,84 +95,9 @@ impl Orm {
         .is_ok()
     }
 
-    /// Creates card.
-    pub async fn create_card(
-        &self,
-        card_options: CreateCard,
-        user_id: i64,
-    ) -> Option<CreateCardResponse> {
-        sqlx::query_scalar!(
-            "SELECT 1 FROM decks WHERE id = $1 AND owner_id = $2",
-            card_options.deck_id,
-            user_id
-        )
-        .fetch_one(self.db.borrow())
-        .await
-        .ok()?;
-
-        if card_options.back.is_empty() {
-            return None;
-        }
-
-        let front_id = self.snowflake.gen_id();
-
-        let mut query = format!(
-            r#"
-            INSERT INTO faces
-            VALUES
-                ({}, $1, $2, $3),
-        "#,
-            front_id
-        );
-
-        let mut back_ids = Vec::with_capacity(card_options.back.len());
-
-        for a in 0..card_options.back.len() {
-            let back_id = self.snowflake.gen_id();
-            back_ids.push(back_id);
-            query += &format!("({}, $1, ${}, ${})", back_id, a * 2 + 4, a * 2 + 5)[..];
-            if a != card_options.back.len() - 1 {
-                query += ", "
-            };
-        }
-
-        let mut query = sqlx::query(&query[..])
-            .bind(user_id)
-            .bind(card_options.front.0)
-            .bind(&card_options.front.1);
-
-        for back_face in card_options.back {
-            query = query.bind(back_face.0).bind(back_face.1);
-        }
-
-        query.fetch_optional(self.db.borrow()).await.ok()?;
-
-        let card_id = self.snowflake.gen_id();
-        sqlx::query!(
-            r#"
-                INSERT INTO cards
-                SELECT $1, $2, $3, $4, $5, NULL;
-            "#,
-            card_id,
-            user_id,
-            card_options.deck_id,
-            front_id,
-            &*back_ids
-        )
-        .fetch_optional(self.db.borrow())
-        .await
-        .ok()?;
-
-        Some(CreateCardResponse {
-            card_id,
-            front: front_id,
-            back: back_ids,
-        })
-    }
-
     pub async fn home(&self, user_id: i64) -> Option<HomeResponse> {
         let decks = sqlx::query_scalar!(
-            "SELECT array_agg(id) as \"arr!\" FROM decks WHERE owner_id = $1",
+            "SELECT array_agg(id ORDER BY position, id) as \"arr!\" FROM decks WHERE owner_id = $1",
             user_id
         )
         .fetch_one(self.db.borrow())
@@ -162,6 +119,29 @@ impl Orm {
 
         Some(HomeResponse { decks, cards })
     }
+
+    /// Gets cards of deck whose front face is a dictionary word.
+    pub async fn deck_quiz_cards(&self, deck_id: i64, user_id: i64) -> Vec<QuizCard> {
+        sqlx::query_as!(
+            QuizCard,
+            r#"
+                SELECT cards.id, faces.data AS "word!"
+                FROM cards
+                JOIN faces ON faces.id = cards.front
+                WHERE
+                    cards.deck_id = $1 AND
+                    cards.owner_id = $2 AND
+                    faces.extension_id = ANY($3) AND
+                    faces.data IS NOT NULL
+            "#,
+            deck_id,
+            user_id,
+            &WORD_EXTENSIONS
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .unwrap_or(vec![])
+    }
 }
 
 #[derive(Serialize)]
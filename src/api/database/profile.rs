@@ -0,0 +1,164 @@
+use super::{account::Profile, decks::deserialize_some, Orm};
+
+use crate::api::error::api_error;
+
+use std::borrow::Borrow;
+
+use serde::Deserialize;
+
+use validator::{Validate, ValidationError};
+
+/// Usernames that could be mistaken for the service or its routes.
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "api",
+    "help",
+    "kino",
+    "me",
+    "moderator",
+    "null",
+    "root",
+    "settings",
+    "support",
+    "system",
+    "user",
+    "users",
+];
+
+/// Profile fields to change, missing fields are kept.
+#[derive(Debug, Deserialize, Validate)]
+pub struct EditProfile {
+    #[validate(length(min = 3, max = 24), custom(function = "validate_username"))]
+    pub username: Option<String>,
+    /// `Some(None)` clears name.
+    #[validate(length(min = 1, max = 64))]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub name: Option<Option<String>>,
+    /// `Some(None)` clears picture.
+    #[validate(url, length(max = 2048))]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub picture: Option<Option<String>>,
+}
+
+/// Profile update error.
+#[derive(Debug)]
+pub enum ProfileError {
+    UserNotFound,
+    UsernameTaken,
+    Database(sqlx::Error),
+}
+
+// username conflicts are reported like validation errors of the field
+api_error!(ProfileError custom_response {
+    Self::UserNotFound => (NOT_FOUND, "user_not_found", "User does not exist."),
+    Self::UsernameTaken => (CONFLICT, "username_taken", "Username is taken."),
+    Self::Database(error) => (INTERNAL_SERVER_ERROR, "database", error),
+});
+
+impl From<sqlx::Error> for ProfileError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => Self::UserNotFound,
+            error
+                if error
+                    .as_database_error()
+                    .is_some_and(|error| error.is_unique_violation()) =>
+            {
+                Self::UsernameTaken
+            }
+            error => Self::Database(error),
+        }
+    }
+}
+
+// Lowercase ascii letters, digits and underscores, starting with a letter.
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if !username.starts_with(|c: char| c.is_ascii_lowercase())
+        || !username
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(ValidationError::new("invalid_characters"));
+    }
+    if RESERVED_USERNAMES.contains(&username) {
+        return Err(ValidationError::new("reserved"));
+    }
+    Ok(())
+}
+
+impl Orm {
+    /// Updates profile of user.
+    pub async fn edit_profile(
+        &self,
+        profile: EditProfile,
+        user_id: i64,
+    ) -> Result<Profile, ProfileError> {
+        Ok(sqlx::query_as!(
+            Profile,
+            r#"
+                UPDATE users SET
+                    username = COALESCE($2, username),
+                    name = CASE WHEN $3 THEN $4 ELSE name END,
+                    picture = CASE WHEN $5 THEN $6 ELSE picture END
+                WHERE id = $1
+                RETURNING email, username, name, picture
+            "#,
+            user_id,
+            profile.username,
+            profile.name.is_some(),
+            profile.name.flatten(),
+            profile.picture.is_some(),
+            profile.picture.flatten()
+        )
+        .fetch_one(self.db.borrow())
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(username: &str) -> Option<String> {
+        validate_username(username)
+            .err()
+            .map(|error| error.code.into_owned())
+    }
+
+    #[test]
+    fn username_characters() {
+        assert_eq!(error("kino_user42"), None);
+        assert_eq!(error("a1"), None);
+        for username in [
+            "Kino",
+            "1kino",
+            "_kino",
+            "kino-user",
+            "kino user",
+            "kinö",
+            "",
+        ] {
+            assert_eq!(error(username).as_deref(), Some("invalid_characters"));
+        }
+    }
+
+    #[test]
+    fn reserved_usernames() {
+        assert_eq!(error("admin").as_deref(), Some("reserved"));
+        assert_eq!(error("settings").as_deref(), Some("reserved"));
+        assert_eq!(error("admins"), None);
+    }
+
+    #[test]
+    fn username_length() {
+        let profile = |username: &str| EditProfile {
+            username: Some(username.to_string()),
+            name: None,
+            picture: None,
+        };
+        assert!(profile("kin").validate().is_ok());
+        assert!(profile("ki").validate().is_err());
+        assert!(profile(&"k".repeat(25)).validate().is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use super::{Face, Orm};
+
+use std::borrow::Borrow;
+
+use serde::{Deserialize, Serialize};
+
+/// Review session options.
+#[derive(Deserialize)]
+pub struct QueueQuery {
+    /// New cards per day, at most [`QueueQuery::MAX_NEW_LIMIT`].
+    #[serde(default = "QueueQuery::default_new_limit")]
+    pub new_limit: i64,
+    /// Reviews per day, at most [`QueueQuery::MAX_REVIEW_LIMIT`].
+    #[serde(default = "QueueQuery::default_review_limit")]
+    pub review_limit: i64,
+    /// IANA time zone name for day boundaries, e.g. `Europe/Istanbul`.
+    #[serde(default = "QueueQuery::default_tz")]
+    pub tz: String,
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "QueueQuery::default_per_page")]
+    pub per_page: usize,
+}
+
+impl QueueQuery {
+    pub const MAX_PER_PAGE: usize = 64;
+    pub const MAX_NEW_LIMIT: i64 = 500;
+    pub const MAX_REVIEW_LIMIT: i64 = 2000;
+
+    fn default_new_limit() -> i64 {
+        20
+    }
+
+    fn default_review_limit() -> i64 {
+        200
+    }
+
+    fn default_tz() -> String {
+        String::from("UTC")
+    }
+
+    fn default_per_page() -> usize {
+        20
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueueCard {
+    pub id: i64,
+    pub deck_id: i64,
+    pub front: i64,
+    pub back: Vec<i64>,
+    /// `None` for new cards.
+    pub due_at: Option<chrono::NaiveDateTime>,
+}
+
+/// A page of today's review session, due cards first and then new cards.
+#[derive(Debug, Serialize)]
+pub struct QueueResponse {
+    pub total: usize,
+    pub page: usize,
+    pub cards: Vec<QueueCard>,
+    /// Faces of the cards in this page.
+    pub faces: Vec<Face>,
+}
+
+impl Orm {
+    /// Builds review session of user. Returns `None` if time zone is unknown.
+    pub async fn queue(&self, user_id: i64, query: QueueQuery) -> Option<QueueResponse> {
+        let date = sqlx::query_scalar!(
+            r#"
+                SELECT local_date(LOCALTIMESTAMP, name) AS "today!"
+                FROM pg_timezone_names
+                WHERE name = $1
+            "#,
+            query.tz
+        )
+        .fetch_optional(self.db.borrow())
+        .await
+        .ok()??;
+
+        // cards that were new before their review count against new card limit
+        let today = sqlx::query!(
+            r#"
+                SELECT
+                    COUNT(DISTINCT card_id) FILTER (WHERE previous_done_at IS NULL) AS "new!",
+                    COUNT(DISTINCT card_id) FILTER (WHERE previous_done_at IS NOT NULL) AS "reviewed!"
+                FROM reviews
+                WHERE
+                    owner_id = $1 AND
+                    -- local days are within a day of server time, so that the index is used
+                    reviewed_at >= LOCALTIMESTAMP - interval '2 days' AND
+                    local_date(reviewed_at, $2) = $3
+            "#,
+            user_id,
+            query.tz,
+            date
+        )
+        .fetch_one(self.db.borrow())
+        .await
+        .ok()?;
+
+        let due_limit =
+            (query.review_limit.clamp(0, QueueQuery::MAX_REVIEW_LIMIT) - today.reviewed).max(0);
+        let new_limit = (query.new_limit.clamp(0, QueueQuery::MAX_NEW_LIMIT) - today.new).max(0);
+
+        let total = sqlx::query_scalar!(
+            r#"
+                SELECT
+                    LEAST(
+                        (
+                            SELECT COUNT(*)
+                            FROM card_schedules
+                            WHERE owner_id = $1 AND due_at <= LOCALTIMESTAMP
+                        ),
+                        $2
+                    ) +
+                    LEAST(
+                        (SELECT COUNT(*) FROM cards WHERE owner_id = $1 AND done_at IS NULL),
+                        $3
+                    ) AS "total!"
+            "#,
+            user_id,
+            due_limit,
+            new_limit
+        )
+        .fetch_one(self.db.borrow())
+        .await
+        .ok()?;
+
+        let per_page = query.per_page.clamp(1, QueueQuery::MAX_PER_PAGE);
+        let offset = i64::try_from(query.page.saturating_mul(per_page)).unwrap_or(i64::MAX);
+        let cards = sqlx::query_as!(
+            QueueCard,
+            r#"
+                SELECT id AS "id!", deck_id AS "deck_id!", front AS "front!", back AS "back!", due_at
+                FROM (
+                    (
+                        SELECT
+                            cards.id, cards.deck_id, cards.front, cards.back,
+                            card_schedules.due_at, 0 AS part
+                        FROM cards
+                        JOIN card_schedules ON card_schedules.id = cards.id
+                        WHERE cards.owner_id = $1 AND card_schedules.due_at <= LOCALTIMESTAMP
+                        ORDER BY card_schedules.due_at, cards.id
+                        LIMIT $2
+                    )
+                    UNION ALL
+                    (
+                        SELECT id, deck_id, front, back, NULL::timestamp, 1 AS part
+                        FROM cards
+                        WHERE owner_id = $1 AND done_at IS NULL
+                        ORDER BY id
+                        LIMIT $3
+                    )
+                ) AS queue
+                ORDER BY part, due_at, id
+                OFFSET $4
+                LIMIT $5
+            "#,
+            user_id,
+            due_limit,
+            new_limit,
+            offset,
+            per_page as i64
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?;
+
+        let face_ids = cards
+            .iter()
+            .flat_map(|card| [card.front].into_iter().chain(card.back.iter().copied()))
+            .collect::<Vec<_>>();
+        let faces = sqlx::query_as!(
+            Face,
+            "SELECT * FROM faces WHERE id = ANY($1) AND owner_id = $2",
+            &face_ids,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?;
+
+        Some(QueueResponse {
+            total: total as usize,
+            page: query.page,
+            cards,
+            faces,
+        })
+    }
+}
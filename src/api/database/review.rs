@@ -0,0 +1,399 @@
+use super::{Orm, Review};
+
+use crate::api::scheduler::{CardSchedule, ReviewGrade, Scheduler};
+
+use std::borrow::Borrow;
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use sqlx::{Postgres, Transaction};
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    pub grade: ReviewGrade,
+    /// Time spent answering the card in milliseconds.
+    pub elapsed_ms: Option<i32>,
+}
+
+/// Card state after a review.
+#[derive(Debug, Serialize)]
+pub struct ReviewResponse {
+    pub card_id: i64,
+    pub deck_id: i64,
+    pub done_at: Option<NaiveDateTime>,
+    #[serde(flatten)]
+    pub schedule: CardSchedule,
+}
+
+// Card columns that reviews change, locked until end of the transaction.
+struct CardState {
+    id: i64,
+    deck_id: i64,
+    done_at: Option<NaiveDateTime>,
+    schedule: CardSchedule,
+    /// Card interval for SM-2 decks, deck interval for Leitner decks.
+    interval_days: f64,
+    level: i32,
+    scheduler: Scheduler,
+    now: NaiveDateTime,
+}
+
+impl Orm {
+    /// Records a graded review and schedules card with its deck's scheduler.
+    pub async fn review_card(
+        &self,
+        card_id: i64,
+        user_id: i64,
+        review: ReviewRequest,
+    ) -> Option<ReviewResponse> {
+        let mut tx = self.db.begin().await.ok()?;
+
+        let card = Self::card_states(&mut tx, &[card_id], user_id)
+            .await
+            .pop()?;
+
+        let (deck_id, schedule, next_interval_days) = match card.scheduler {
+            Scheduler::Sm2 => {
+                let schedule = card.schedule.review(review.grade, card.now);
+                (card.deck_id, schedule, schedule.interval_days)
+            }
+            Scheduler::Leitner => {
+                // highest deck not above the new level, or the lowest deck
+                let deck = sqlx::query!(
+                    r#"
+                        SELECT id, EXTRACT(EPOCH FROM interval)::float8 / 86400 AS "interval_days!"
+                        FROM decks
+                        WHERE owner_id = $1 AND scheduler = 'leitner'
+                        ORDER BY
+                            level <= $2 DESC,
+                            CASE WHEN level <= $2 THEN -level ELSE level END
+                        LIMIT 1
+                    "#,
+                    user_id,
+                    review.grade.leitner_level(card.level)
+                )
+                .fetch_one(&mut *tx)
+                .await
+                .ok()?;
+
+                // due time of Leitner cards comes from deck interval
+                let schedule = CardSchedule {
+                    due_at: None,
+                    ..card.schedule
+                };
+                (deck.id, schedule, deck.interval_days)
+            }
+        };
+
+        sqlx::query!(
+            r#"
+                UPDATE cards
+                SET
+                    deck_id = $3,
+                    done_at = $4,
+                    due_at = $5,
+                    ease = $6,
+                    interval_days = $7,
+                    repetitions = $8,
+                    lapses = $9
+                WHERE id = $1 AND owner_id = $2
+            "#,
+            card_id,
+            user_id,
+            deck_id,
+            card.now,
+            schedule.due_at,
+            schedule.ease,
+            schedule.interval_days,
+            schedule.repetitions,
+            schedule.lapses
+        )
+        .execute(&mut *tx)
+        .await
+        .ok()?;
+
+        self.log_review(
+            &mut tx,
+            &card,
+            user_id,
+            Some(review.grade),
+            review.elapsed_ms,
+            next_interval_days,
+        )
+        .await?;
+
+        tx.commit().await.ok()?;
+
+        Some(ReviewResponse {
+            card_id,
+            deck_id,
+            done_at: Some(card.now),
+            schedule,
+        })
+    }
+
+    /// Marks cards as done without grading. Returns new `done_at` of updated cards.
+    pub async fn mark_done(&self, ids: &[i64], user_id: i64) -> Vec<(i64, Option<NaiveDateTime>)> {
+        let Ok(mut tx) = self.db.begin().await else {
+            return vec![];
+        };
+
+        let cards = Self::card_states(&mut tx, ids, user_id).await;
+        let mut done = Vec::with_capacity(cards.len());
+        for card in cards {
+            let updated = sqlx::query!(
+                "UPDATE cards SET done_at = $2 WHERE id = $1",
+                card.id,
+                card.now
+            )
+            .execute(&mut *tx)
+            .await;
+
+            let interval_days = card.interval_days;
+            if updated.is_err()
+                || self
+                    .log_review(&mut tx, &card, user_id, None, None, interval_days)
+                    .await
+                    .is_none()
+            {
+                return vec![];
+            }
+            done.push((card.id, Some(card.now)));
+        }
+
+        if tx.commit().await.is_err() {
+            return vec![];
+        }
+        done
+    }
+
+    /// Moves card to another deck of user, marking it done.
+    pub async fn move_card(&self, card_id: i64, user_id: i64, deck_id: i64) -> Option<()> {
+        let mut tx = self.db.begin().await.ok()?;
+
+        let card = Self::card_states(&mut tx, &[card_id], user_id)
+            .await
+            .pop()?;
+
+        let next_interval_days = sqlx::query_scalar!(
+            r#"
+                UPDATE cards
+                SET
+                    done_at = $4,
+                    deck_id = $3
+                FROM decks
+                WHERE
+                    cards.id = $1 AND
+                    decks.id = $3 AND
+                    decks.owner_id = $2
+                RETURNING
+                    CASE
+                        WHEN decks.scheduler = 'sm2' THEN cards.interval_days
+                        ELSE EXTRACT(EPOCH FROM decks.interval)::float8 / 86400
+                    END AS "interval_days!"
+            "#,
+            card_id,
+            user_id,
+            deck_id,
+            card.now
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .ok()?;
+
+        self.log_review(&mut tx, &card, user_id, None, None, next_interval_days)
+            .await?;
+
+        tx.commit().await.ok()
+    }
+
+    /// Review history of a card, latest first.
+    pub async fn card_reviews(&self, card_id: i64, user_id: i64) -> Vec<Review> {
+        sqlx::query_as!(
+            Review,
+            r#"
+                SELECT
+                    id, card_id, owner_id, reviewed_at, grade AS "grade: ReviewGrade",
+                    elapsed_ms, previous_interval_days, next_interval_days, previous_deck_id,
+                    previous_done_at, previous_due_at, previous_ease, previous_repetitions,
+                    previous_lapses
+                FROM reviews
+                WHERE card_id = $1 AND owner_id = $2
+                ORDER BY reviewed_at DESC, id DESC
+                LIMIT 256
+            "#,
+            card_id,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .unwrap_or(vec![])
+    }
+
+    /// Undoes the last review of card by restoring the card state before it.
+    pub async fn undo_review(&self, card_id: i64, user_id: i64) -> Option<ReviewResponse> {
+        let mut tx = self.db.begin().await.ok()?;
+
+        let card = sqlx::query!(
+            r#"
+                DELETE FROM reviews
+                WHERE id = (
+                    SELECT reviews.id FROM reviews
+                    JOIN cards ON cards.id = reviews.card_id
+                    WHERE reviews.card_id = $1 AND reviews.owner_id = $2
+                    ORDER BY reviews.reviewed_at DESC, reviews.id DESC
+                    LIMIT 1
+                    FOR UPDATE
+                )
+                RETURNING
+                    previous_deck_id, previous_done_at, previous_due_at, previous_ease,
+                    previous_interval_days, previous_repetitions, previous_lapses
+            "#,
+            card_id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .ok()?;
+
+        let card = sqlx::query!(
+            r#"
+                UPDATE cards
+                SET
+                    deck_id = $3,
+                    done_at = $4,
+                    due_at = $5,
+                    ease = $6,
+                    interval_days = CASE
+                        WHEN decks.scheduler = 'sm2' THEN $7
+                        ELSE cards.interval_days
+                    END,
+                    repetitions = $8,
+                    lapses = $9
+                FROM decks
+                WHERE cards.id = $1 AND cards.owner_id = $2 AND decks.id = $3
+                RETURNING
+                    cards.deck_id, cards.done_at, cards.due_at, cards.ease, cards.interval_days,
+                    cards.repetitions, cards.lapses
+            "#,
+            card_id,
+            user_id,
+            card.previous_deck_id,
+            card.previous_done_at,
+            card.previous_due_at,
+            card.previous_ease,
+            card.previous_interval_days,
+            card.previous_repetitions,
+            card.previous_lapses
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .ok()?;
+
+        tx.commit().await.ok()?;
+
+        Some(ReviewResponse {
+            card_id,
+            deck_id: card.deck_id,
+            done_at: card.done_at,
+            schedule: CardSchedule {
+                due_at: card.due_at,
+                ease: card.ease,
+                interval_days: card.interval_days,
+                repetitions: card.repetitions,
+                lapses: card.lapses,
+            },
+        })
+    }
+
+    // Locks cards of user and reads their state.
+    async fn card_states(
+        tx: &mut Transaction<'_, Postgres>,
+        ids: &[i64],
+        user_id: i64,
+    ) -> Vec<CardState> {
+        sqlx::query!(
+            r#"
+                SELECT
+                    cards.id, cards.deck_id, cards.done_at, cards.due_at, cards.ease,
+                    cards.interval_days, cards.repetitions, cards.lapses, decks.level,
+                    decks.scheduler AS "scheduler: Scheduler",
+                    CASE
+                        WHEN decks.scheduler = 'sm2' THEN cards.interval_days
+                        ELSE EXTRACT(EPOCH FROM decks.interval)::float8 / 86400
+                    END AS "effective_interval_days!",
+                    LOCALTIMESTAMP AS "now!"
+                FROM cards
+                JOIN decks ON decks.id = cards.deck_id
+                WHERE cards.id = ANY($1) AND cards.owner_id = $2
+                FOR UPDATE OF cards
+            "#,
+            ids,
+            user_id
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .unwrap_or(vec![])
+        .into_iter()
+        .map(|card| CardState {
+            id: card.id,
+            deck_id: card.deck_id,
+            done_at: card.done_at,
+            schedule: CardSchedule {
+                due_at: card.due_at,
+                ease: card.ease,
+                interval_days: card.interval_days,
+                repetitions: card.repetitions,
+                lapses: card.lapses,
+            },
+            interval_days: card.effective_interval_days,
+            level: card.level,
+            scheduler: card.scheduler,
+            now: card.now,
+        })
+        .collect()
+    }
+
+    // Writes a review of card to history with its state before the review.
+    async fn log_review(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        card: &CardState,
+        user_id: i64,
+        grade: Option<ReviewGrade>,
+        elapsed_ms: Option<i32>,
+        next_interval_days: f64,
+    ) -> Option<()> {
+        sqlx::query!(
+            r#"
+                INSERT INTO reviews (
+                    id, card_id, owner_id, reviewed_at, grade, elapsed_ms,
+                    previous_interval_days, next_interval_days, previous_deck_id,
+                    previous_done_at, previous_due_at, previous_ease, previous_repetitions,
+                    previous_lapses
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            "#,
+            self.snowflake.gen_id(),
+            card.id,
+            user_id,
+            card.now,
+            grade as Option<ReviewGrade>,
+            elapsed_ms,
+            card.interval_days,
+            next_interval_days,
+            card.deck_id,
+            card.done_at,
+            card.schedule.due_at,
+            card.schedule.ease,
+            card.schedule.repetitions,
+            card.schedule.lapses
+        )
+        .execute(&mut **tx)
+        .await
+        .ok()
+        .map(|_| ())
+    }
+}
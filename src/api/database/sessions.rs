@@ -0,0 +1,73 @@
+use super::Orm;
+
+use std::borrow::Borrow;
+
+use chrono::NaiveDateTime;
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether the session is the one making the request.
+    pub current: bool,
+}
+
+impl Orm {
+    /// Sessions of user that can still be refreshed, most recently seen first.
+    pub async fn sessions(&self, user_id: i64, current_id: i64) -> Option<Vec<Session>> {
+        sqlx::query_as!(
+            Session,
+            r#"
+                SELECT id, created_at, last_seen_at, user_agent, ip, id = $2 AS "current!"
+                FROM sessions
+                WHERE user_id = $1 AND EXISTS(
+                    SELECT 1 FROM refresh_tokens
+                    WHERE session_id = sessions.id AND expires_at >= LOCALTIMESTAMP
+                )
+                ORDER BY last_seen_at DESC
+            "#,
+            user_id,
+            current_id
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()
+    }
+
+    /// Ends a session of user with its refresh tokens. Returns whether session existed.
+    pub async fn revoke_session(&self, id: i64, user_id: i64) -> Option<bool> {
+        sqlx::query!(
+            "DELETE FROM sessions WHERE id = $1 AND user_id = $2",
+            id,
+            user_id
+        )
+        .execute(self.db.borrow())
+        .await
+        .ok()
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Ends all sessions of user.
+    pub async fn revoke_sessions(&self, user_id: i64) -> Option<u64> {
+        sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user_id)
+            .execute(self.db.borrow())
+            .await
+            .ok()
+            .map(|result| result.rows_affected())
+    }
+
+    /// Updates last seen time of a session.
+    pub async fn touch_session(&self, id: i64) {
+        let _ = sqlx::query!(
+            "UPDATE sessions SET last_seen_at = LOCALTIMESTAMP WHERE id = $1",
+            id
+        )
+        .execute(self.db.borrow())
+        .await;
+    }
+}
@@ -0,0 +1,316 @@
+use super::Orm;
+
+use std::borrow::Borrow;
+
+use chrono::{Days, NaiveDate, NaiveDateTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    /// IANA time zone name for day boundaries, e.g. `Europe/Istanbul`.
+    #[serde(default = "StatsQuery::default_tz")]
+    pub tz: String,
+    /// Number of past days including today.
+    #[serde(default = "StatsQuery::default_days")]
+    pub days: i32,
+    /// Number of upcoming days including today.
+    #[serde(default = "StatsQuery::default_days")]
+    pub forecast_days: i32,
+}
+
+impl StatsQuery {
+    pub const MAX_DAYS: i32 = 365;
+
+    fn default_tz() -> String {
+        String::from("UTC")
+    }
+
+    fn default_days() -> i32 {
+        30
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Graded reviews that are not `again`.
+    pub correct: i64,
+    pub accuracy: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeckStats {
+    pub deck_id: i64,
+    pub reviews: i64,
+    pub correct: i64,
+    pub accuracy: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelStats {
+    pub level: i32,
+    pub cards: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    /// Cards that become due on this day. Overdue cards are counted on today.
+    pub cards: i64,
+}
+
+/// Learning statistics of user.
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    pub days: Vec<DayStats>,
+    pub decks: Vec<DeckStats>,
+    /// Ratio of remembered cards among graded reviews of already learned cards.
+    pub retention: Option<f64>,
+    /// Cards per Leitner level.
+    pub levels: Vec<LevelStats>,
+    pub forecast: Vec<ForecastDay>,
+    /// Consecutive days with reviews, ending today or yesterday. Counted up to
+    /// [`StatsQuery::MAX_DAYS`].
+    pub streak: i64,
+}
+
+impl Orm {
+    /// Aggregates review history of user. Returns `None` if time zone is unknown.
+    pub async fn stats(&self, user_id: i64, query: StatsQuery) -> Option<StatsResponse> {
+        let tz = &query.tz;
+        let days = query.days.clamp(1, StatsQuery::MAX_DAYS);
+        let forecast_days = query.forecast_days.clamp(1, StatsQuery::MAX_DAYS);
+
+        let today = sqlx::query_scalar!(
+            r#"
+                SELECT local_date(LOCALTIMESTAMP, name) AS "today!"
+                FROM pg_timezone_names
+                WHERE name = $1
+            "#,
+            tz
+        )
+        .fetch_optional(self.db.borrow())
+        .await
+        .ok()??;
+
+        let first_day = today - Days::new(days as u64 - 1);
+
+        let days = sqlx::query!(
+            r#"
+                SELECT
+                    dates.date::date AS "date!",
+                    COUNT(reviews.id) AS "reviews!",
+                    COUNT(reviews.id) FILTER (WHERE reviews.grade <> 'again') AS "correct!",
+                    COUNT(reviews.grade) AS "graded!"
+                FROM generate_series($3::date - ($4 - 1), $3::date, INTERVAL '1 day') AS dates(date)
+                LEFT JOIN reviews ON
+                    reviews.owner_id = $1 AND
+                    reviews.reviewed_at >= $5 AND
+                    local_date(reviews.reviewed_at, $2) = dates.date::date
+                GROUP BY dates.date
+                ORDER BY dates.date
+            "#,
+            user_id,
+            tz,
+            today,
+            days,
+            since(first_day)
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?
+        .into_iter()
+        .map(|day| DayStats {
+            date: day.date,
+            reviews: day.reviews,
+            correct: day.correct,
+            accuracy: ratio(day.correct, day.graded),
+        })
+        .collect::<Vec<_>>();
+
+        let decks = sqlx::query!(
+            r#"
+                SELECT
+                    previous_deck_id AS deck_id,
+                    COUNT(*) AS "reviews!",
+                    COUNT(*) FILTER (WHERE grade <> 'again') AS "correct!",
+                    COUNT(grade) AS "graded!"
+                FROM reviews
+                WHERE
+                    owner_id = $1 AND
+                    reviewed_at >= $4 AND
+                    local_date(reviewed_at, $2) >= $3
+                GROUP BY previous_deck_id
+                ORDER BY previous_deck_id
+            "#,
+            user_id,
+            tz,
+            first_day,
+            since(first_day)
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?
+        .into_iter()
+        .map(|deck| DeckStats {
+            deck_id: deck.deck_id,
+            reviews: deck.reviews,
+            correct: deck.correct,
+            accuracy: ratio(deck.correct, deck.graded),
+        })
+        .collect();
+
+        let retention = sqlx::query!(
+            r#"
+                SELECT
+                    COUNT(*) FILTER (WHERE grade <> 'again') AS "remembered!",
+                    COUNT(*) AS "graded!"
+                FROM reviews
+                WHERE
+                    owner_id = $1 AND
+                    grade IS NOT NULL AND
+                    previous_done_at IS NOT NULL AND
+                    reviewed_at >= $4 AND
+                    local_date(reviewed_at, $2) >= $3
+            "#,
+            user_id,
+            tz,
+            first_day,
+            since(first_day)
+        )
+        .fetch_one(self.db.borrow())
+        .await
+        .ok()?;
+
+        let levels = sqlx::query_as!(
+            LevelStats,
+            r#"
+                SELECT decks.level, COUNT(cards.id) AS "cards!"
+                FROM decks
+                LEFT JOIN cards ON cards.deck_id = decks.id
+                WHERE decks.owner_id = $1 AND decks.scheduler = 'leitner'
+                GROUP BY decks.level
+                ORDER BY decks.level
+            "#,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?;
+
+        let forecast = sqlx::query_as!(
+            ForecastDay,
+            r#"
+                SELECT dates.date::date AS "date!", COUNT(card_schedules.id) AS "cards!"
+                FROM generate_series($3::date, $3::date + ($4 - 1), INTERVAL '1 day') AS dates(date)
+                LEFT JOIN card_schedules ON
+                    card_schedules.owner_id = $1 AND
+                    card_schedules.due_at IS NOT NULL AND
+                    GREATEST(local_date(card_schedules.due_at, $2), $3) = dates.date::date
+                GROUP BY dates.date
+                ORDER BY dates.date
+            "#,
+            user_id,
+            tz,
+            today,
+            forecast_days
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?;
+
+        let streak_start = today - Days::new(StatsQuery::MAX_DAYS as u64);
+        let review_dates = sqlx::query_scalar!(
+            r#"
+                SELECT DISTINCT local_date(reviewed_at, $2) AS "date!"
+                FROM reviews
+                WHERE owner_id = $1 AND reviewed_at >= $4 AND local_date(reviewed_at, $2) > $3
+                ORDER BY 1 DESC
+            "#,
+            user_id,
+            tz,
+            streak_start,
+            since(streak_start)
+        )
+        .fetch_all(self.db.borrow())
+        .await
+        .ok()?;
+
+        Some(StatsResponse {
+            days,
+            decks,
+            retention: ratio(retention.remembered, retention.graded),
+            levels,
+            forecast,
+            streak: streak(&review_dates, today),
+        })
+    }
+}
+
+// Server time before start of a local date. Time zones are less than two days apart, so that
+// the bound lets `(owner_id, reviewed_at)` index narrow reviews of a window.
+fn since(date: NaiveDate) -> NaiveDateTime {
+    (date - Days::new(2)).and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn ratio(count: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| count as f64 / total as f64)
+}
+
+// Counts consecutive days in descending dates. A streak is not broken until today ends.
+fn streak(dates: &[NaiveDate], today: NaiveDate) -> i64 {
+    let mut expected = today;
+    let mut streak = 0;
+    for &date in dates {
+        if date > expected {
+            continue;
+        }
+        if date != expected && !(streak == 0 && date == expected.pred_opt().unwrap_or(date)) {
+            break;
+        }
+        streak += 1;
+        expected = date.pred_opt().unwrap_or(date);
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days_ago(today: NaiveDate, days: &[u64]) -> Vec<NaiveDate> {
+        days.iter().map(|&days| today - Days::new(days)).collect()
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    #[test]
+    fn streak_counts_consecutive_days_until_today() {
+        assert_eq!(streak(&days_ago(today(), &[0, 1, 2, 4]), today()), 3);
+        assert_eq!(streak(&days_ago(today(), &[0]), today()), 1);
+    }
+
+    #[test]
+    fn streak_is_kept_until_today_ends() {
+        // today is not reviewed yet
+        assert_eq!(streak(&days_ago(today(), &[1, 2, 3, 5]), today()), 3);
+        assert_eq!(streak(&days_ago(today(), &[1]), today()), 1);
+    }
+
+    #[test]
+    fn streak_is_broken_by_missed_day() {
+        assert_eq!(streak(&days_ago(today(), &[2, 3]), today()), 0);
+        assert_eq!(streak(&[], today()), 0);
+    }
+
+    #[test]
+    fn streak_skips_days_after_today() {
+        let mut dates = vec![today() + Days::new(1)];
+        dates.extend(days_ago(today(), &[0, 1]));
+        assert_eq!(streak(&dates, today()), 2);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::api::scheduler::{ReviewGrade, Scheduler};
+
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::types::PgInterval, FromRow};
 
@@ -9,11 +11,15 @@ pub struct Deck {
     #[serde(with = "PgIntervalRemote")]
     pub interval: PgInterval,
     pub level: i32,
+    pub scheduler: Scheduler,
+    pub name: String,
+    pub description: Option<String>,
+    pub position: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 #[serde(remote = "PgInterval")]
-struct PgIntervalRemote {
+pub(super) struct PgIntervalRemote {
     pub months: i32,
     pub days: i32,
     pub microseconds: i64,
@@ -27,6 +33,11 @@ pub struct Card {
     pub front: i64,
     pub back: Vec<i64>,
     pub done_at: Option<chrono::NaiveDateTime>,
+    pub due_at: Option<chrono::NaiveDateTime>,
+    pub ease: f64,
+    pub interval_days: f64,
+    pub repetitions: i32,
+    pub lapses: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -44,3 +55,21 @@ pub struct Extension {
     pub name: String,
     pub data: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Review {
+    pub id: i64,
+    pub card_id: i64,
+    pub owner_id: i64,
+    pub reviewed_at: chrono::NaiveDateTime,
+    pub grade: Option<ReviewGrade>,
+    pub elapsed_ms: Option<i32>,
+    pub previous_interval_days: f64,
+    pub next_interval_days: f64,
+    pub previous_deck_id: i64,
+    pub previous_done_at: Option<chrono::NaiveDateTime>,
+    pub previous_due_at: Option<chrono::NaiveDateTime>,
+    pub previous_ease: f64,
+    pub previous_repetitions: i32,
+    pub previous_lapses: i32,
+}
@@ -0,0 +1,216 @@
+use super::Orm;
+
+use crate::api::error::api_error;
+
+use rand::RngCore;
+
+use sha2::{Digest, Sha256};
+
+/// Days a refresh token can be used for. Each rotation starts a new lifetime.
+const REFRESH_TOKEN_DAYS: i32 = 30;
+
+/// User claims to issue an access token for.
+#[derive(Debug)]
+pub struct TokenOwner {
+    pub id: i64,
+    pub email: String,
+    pub username: Option<String>,
+    pub session_id: i64,
+}
+
+/// Refresh token rotation error.
+#[derive(Debug)]
+pub enum RefreshError {
+    /// Token is malformed, unknown or expired.
+    InvalidToken,
+    /// Token was already rotated, its session with this id is deleted.
+    TokenReused(i64),
+    Database(sqlx::Error),
+}
+
+api_error!(RefreshError {
+    Self::InvalidToken => (UNAUTHORIZED, "invalid_token", "Refresh token is invalid."),
+    Self::TokenReused(_) => (UNAUTHORIZED, "token_reused", "Refresh token was already used."),
+    Self::Database(error) => (INTERNAL_SERVER_ERROR, "database", error),
+});
+
+impl From<sqlx::Error> for RefreshError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+// Tokens are `{id}.{secret}`, secret is 32 random bytes in hex.
+fn generate_secret() -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hash_secret(secret: &str) -> Vec<u8> {
+    Sha256::digest(secret.as_bytes()).to_vec()
+}
+
+impl Orm {
+    /// Starts a session of user and issues its first refresh token. Returns session id and
+    /// token.
+    pub async fn issue_refresh_token(
+        &self,
+        user_id: i64,
+        user_agent: Option<&str>,
+        ip: Option<&str>,
+    ) -> Option<(i64, String)> {
+        let session_id = self.snowflake.gen_id();
+        let id = self.snowflake.gen_id();
+        let secret = generate_secret();
+
+        let mut tx = self.db.begin().await.ok()?;
+
+        // sessions without usable tokens are over
+        sqlx::query!(
+            r#"
+                DELETE FROM sessions
+                WHERE user_id = $1 AND NOT EXISTS(
+                    SELECT 1 FROM refresh_tokens
+                    WHERE session_id = sessions.id AND expires_at >= LOCALTIMESTAMP
+                )
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .ok()?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO sessions (id, user_id, created_at, last_seen_at, user_agent, ip)
+                VALUES ($1, $2, LOCALTIMESTAMP, LOCALTIMESTAMP, $3, $4)
+            "#,
+            session_id,
+            user_id,
+            user_agent.map(|user_agent| user_agent.chars().take(256).collect::<String>()),
+            ip.map(|ip| ip.chars().take(45).collect::<String>())
+        )
+        .execute(&mut *tx)
+        .await
+        .ok()?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO refresh_tokens (id, session_id, secret_hash, expires_at)
+                VALUES ($1, $2, $3, LOCALTIMESTAMP + make_interval(days => $4))
+            "#,
+            id,
+            session_id,
+            hash_secret(&secret),
+            REFRESH_TOKEN_DAYS
+        )
+        .execute(&mut *tx)
+        .await
+        .ok()?;
+
+        tx.commit().await.ok()?;
+
+        Some((session_id, format!("{id}.{secret}")))
+    }
+
+    /// Exchanges a refresh token for a new one of the same session. Presenting a rotated token
+    /// again ends the session, as either the client or an attacker holds a stolen copy.
+    pub async fn rotate_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<(String, TokenOwner), RefreshError> {
+        let Some((id, secret)) = token
+            .split_once('.')
+            .and_then(|(id, secret)| Some((id.parse::<i64>().ok()?, secret)))
+        else {
+            return Err(RefreshError::InvalidToken);
+        };
+
+        let mut tx = self.db.begin().await?;
+
+        let stored = sqlx::query!(
+            r#"
+                SELECT
+                    refresh_tokens.session_id, sessions.user_id, refresh_tokens.secret_hash,
+                    refresh_tokens.used_at IS NOT NULL AS "used!",
+                    refresh_tokens.expires_at < LOCALTIMESTAMP AS "expired!"
+                FROM refresh_tokens
+                JOIN sessions ON sessions.id = refresh_tokens.session_id
+                WHERE refresh_tokens.id = $1
+                FOR UPDATE
+            "#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(stored) = stored.filter(|stored| stored.secret_hash == hash_secret(secret)) else {
+            return Err(RefreshError::InvalidToken);
+        };
+        if stored.expired {
+            return Err(RefreshError::InvalidToken);
+        }
+        if stored.used {
+            sqlx::query!("DELETE FROM sessions WHERE id = $1", stored.session_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            tracing::debug!(
+                "Refresh token reused: user_id={} session_id={}",
+                stored.user_id,
+                stored.session_id
+            );
+            return Err(RefreshError::TokenReused(stored.session_id));
+        }
+
+        sqlx::query!(
+            "UPDATE refresh_tokens SET used_at = LOCALTIMESTAMP WHERE id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // expired tokens are no longer needed to detect reuse
+        sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE session_id = $1 AND expires_at < LOCALTIMESTAMP",
+            stored.session_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let new_id = self.snowflake.gen_id();
+        let new_secret = generate_secret();
+        sqlx::query!(
+            r#"
+                INSERT INTO refresh_tokens (id, session_id, secret_hash, expires_at)
+                VALUES ($1, $2, $3, LOCALTIMESTAMP + make_interval(days => $4))
+            "#,
+            new_id,
+            stored.session_id,
+            hash_secret(&new_secret),
+            REFRESH_TOKEN_DAYS
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let owner = sqlx::query_as!(
+            TokenOwner,
+            r#"
+                UPDATE sessions SET last_seen_at = LOCALTIMESTAMP
+                FROM users
+                WHERE sessions.id = $1 AND users.id = sessions.user_id
+                RETURNING users.id, users.email, users.username,
+                    sessions.id AS session_id
+            "#,
+            stored.session_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((format!("{new_id}.{new_secret}"), owner))
+    }
+}
@@ -0,0 +1,610 @@
+use super::{
+    decks::{CreateDeck, DeckInterval},
+    CardError, CreateCard, Face, Orm, BULK_LIMIT,
+};
+
+use crate::api::{
+    anki::{AnkiExport, AnkiExportCard, AnkiPackage, AnkiReview, AnkiRevlog},
+    scheduler::{CardSchedule, Scheduler},
+};
+
+use std::{borrow::Borrow, collections::BTreeMap};
+
+use sqlx::postgres::types::PgInterval;
+
+use serde::{Deserialize, Serialize};
+
+/// Delimited text format of a deck.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    #[default]
+    Csv,
+    Tsv,
+}
+
+impl TableFormat {
+    fn delimiter(self) -> u8 {
+        match self {
+            Self::Csv => b',',
+            Self::Tsv => b'\t',
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Tsv => "text/tab-separated-values; charset=utf-8",
+        }
+    }
+}
+
+/// Maps columns of rows to faces. Empty back columns are skipped.
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: TableFormat,
+    /// Whether first row is a header.
+    #[serde(default)]
+    pub header: bool,
+    /// Column of front face, other columns are back faces.
+    #[serde(default)]
+    pub front_column: usize,
+    /// Extension of front face, `Word` by default.
+    #[serde(default)]
+    pub front_extension: i64,
+    /// Extension of back faces, `Note` by default.
+    #[serde(default = "ImportQuery::default_back_extension")]
+    pub back_extension: i64,
+}
+
+impl ImportQuery {
+    fn default_back_extension() -> i64 {
+        1
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: TableFormat,
+}
+
+#[derive(Deserialize)]
+pub struct AnkiExportQuery {
+    /// Whether graded reviews are exported as review history.
+    #[serde(default)]
+    pub reviews: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportError {
+    /// Line of the row in input, starting from 1.
+    pub line: u64,
+    pub error: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub created: Vec<i64>,
+    pub errors: Vec<ImportError>,
+}
+
+/// Cards parsed from delimited rows, with lines of cards and rows that cannot be parsed.
+pub struct ImportRows {
+    lines: Vec<u64>,
+    cards: Vec<CreateCard>,
+    errors: Vec<ImportError>,
+}
+
+impl ImportRows {
+    /// Parses rows up to [`BULK_LIMIT`] into cards of deck.
+    pub fn parse(deck_id: i64, text: &str, query: &ImportQuery) -> Self {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(query.format.delimiter())
+            .has_headers(query.header)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let mut lines = vec![];
+        let mut cards = vec![];
+        let mut errors = vec![];
+        for (index, record) in reader.records().enumerate() {
+            if index == BULK_LIMIT {
+                errors.push(ImportError {
+                    line: record
+                        .ok()
+                        .and_then(|record| record.position().map(|position| position.line()))
+                        .unwrap_or(0),
+                    error: "too_many_rows",
+                });
+                break;
+            }
+
+            let record = match record {
+                Ok(record) => record,
+                Err(error) => {
+                    errors.push(ImportError {
+                        line: error.position().map_or(0, |position| position.line()),
+                        error: "invalid_row",
+                    });
+                    continue;
+                }
+            };
+
+            let line = record.position().map_or(0, |position| position.line());
+            let Some(front) = record.get(query.front_column) else {
+                errors.push(ImportError {
+                    line,
+                    error: "missing_front",
+                });
+                continue;
+            };
+
+            lines.push(line);
+            cards.push(CreateCard {
+                deck_id,
+                front: (query.front_extension, Some(front.trim().to_owned())),
+                back: record
+                    .iter()
+                    .enumerate()
+                    .filter(|(column, data)| {
+                        *column != query.front_column && !data.trim().is_empty()
+                    })
+                    .map(|(_, data)| (query.back_extension, Some(data.trim().to_owned())))
+                    .collect(),
+            });
+        }
+
+        Self {
+            lines,
+            cards,
+            errors,
+        }
+    }
+
+    /// Number of cards to create.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnkiImportError {
+    pub note_id: i64,
+    pub error: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnkiImportReport {
+    pub decks: Vec<i64>,
+    pub created: Vec<i64>,
+    /// Number of cards whose review intervals are kept.
+    pub scheduled: usize,
+    /// Number of media files that are not imported.
+    pub skipped_media: usize,
+    pub errors: Vec<AnkiImportError>,
+}
+
+impl Orm {
+    /// Imports notes of an Anki package. Each Anki deck with notes becomes an SM-2 deck, first
+    /// field of a note becomes a `Word` face and other fields become `Note` faces. Nothing is
+    /// imported if the import fails.
+    pub async fn import_anki(
+        &self,
+        package: AnkiPackage,
+        user_id: i64,
+    ) -> Result<AnkiImportReport, CardError> {
+        let mut tx = self.db.begin().await?;
+
+        let mut decks = BTreeMap::new();
+        for deck in &package.decks {
+            if !package.notes.iter().any(|note| note.deck_id == deck.id) {
+                continue;
+            }
+
+            let deck_options = CreateDeck {
+                name: deck.name.chars().take(64).collect(),
+                description: None,
+                interval: DeckInterval(PgInterval {
+                    months: 0,
+                    days: 1,
+                    microseconds: 0,
+                }),
+                level: 0,
+                scheduler: Scheduler::Sm2,
+            };
+            let created = self.insert_deck(&mut *tx, deck_options, user_id).await?;
+            decks.insert(deck.id, created.id);
+        }
+
+        let mut report = AnkiImportReport {
+            decks: decks.values().copied().collect(),
+            created: vec![],
+            scheduled: 0,
+            skipped_media: package.media,
+            errors: vec![],
+        };
+
+        let mut notes = vec![];
+        let mut cards = vec![];
+        for note in package.notes {
+            let Some(&deck_id) = decks.get(&note.deck_id) else {
+                report.errors.push(AnkiImportError {
+                    note_id: note.id,
+                    error: "deck_not_found",
+                });
+                continue;
+            };
+
+            let mut fields = note.fields.into_iter();
+            let front = fields.next().unwrap_or_default();
+            if front.is_empty() {
+                report.errors.push(AnkiImportError {
+                    note_id: note.id,
+                    error: "empty_front",
+                });
+                continue;
+            }
+
+            notes.push((note.id, note.review));
+            cards.push(CreateCard {
+                deck_id,
+                front: (0, Some(front)),
+                back: fields
+                    .filter(|field| !field.is_empty())
+                    .map(|field| (1, Some(field)))
+                    .collect(),
+            });
+        }
+
+        let mut scheduled = (vec![], vec![], vec![], vec![], vec![], vec![]);
+        let mut notes = notes.into_iter();
+        while !cards.is_empty() {
+            let chunk = cards
+                .drain(..cards.len().min(BULK_LIMIT))
+                .collect::<Vec<_>>();
+            for ((note_id, review), result) in notes
+                .by_ref()
+                .zip(self.insert_cards(&mut tx, chunk, user_id).await?)
+            {
+                let card = match result {
+                    Ok(card) => card,
+                    Err(error) => {
+                        report.errors.push(AnkiImportError {
+                            note_id,
+                            error: error.code(),
+                        });
+                        continue;
+                    }
+                };
+
+                report.created.push(card.card_id);
+                if let Some(review) = review {
+                    scheduled.0.push(card.card_id);
+                    scheduled.1.push(review.due);
+                    scheduled.2.push(review.interval_days);
+                    scheduled.3.push(review.ease);
+                    scheduled.4.push(review.repetitions);
+                    scheduled.5.push(review.lapses);
+                }
+            }
+        }
+
+        // cards were done an interval before their due time
+        report.scheduled = sqlx::query!(
+            r#"
+                UPDATE cards
+                SET
+                    due_at = to_timestamp(reviews.due)::timestamp,
+                    done_at = to_timestamp(reviews.due)::timestamp
+                        - reviews.interval_days * INTERVAL '1 day',
+                    interval_days = reviews.interval_days,
+                    ease = GREATEST(reviews.ease, $8),
+                    repetitions = reviews.repetitions,
+                    lapses = reviews.lapses
+                FROM unnest(
+                    $1::bigint[], $2::bigint[], $3::float8[], $4::float8[], $5::int[], $6::int[]
+                ) AS reviews(id, due, interval_days, ease, repetitions, lapses)
+                WHERE cards.id = reviews.id AND cards.owner_id = $7
+            "#,
+            &scheduled.0,
+            &scheduled.1,
+            &scheduled.2,
+            &scheduled.3,
+            &scheduled.4,
+            &scheduled.5,
+            user_id,
+            CardSchedule::MIN_EASE
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as usize;
+
+        tx.commit().await?;
+
+        Ok(report)
+    }
+
+    /// Imports parsed rows into deck as cards, reporting rows that fail.
+    pub async fn import_deck(
+        &self,
+        deck_id: i64,
+        rows: ImportRows,
+        user_id: i64,
+    ) -> Result<ImportReport, CardError> {
+        sqlx::query_scalar!(
+            "SELECT 1 FROM decks WHERE id = $1 AND owner_id = $2",
+            deck_id,
+            user_id
+        )
+        .fetch_optional(self.db.borrow())
+        .await?
+        .ok_or(CardError::DeckNotFound)?;
+
+        let ImportRows {
+            lines,
+            cards,
+            mut errors,
+        } = rows;
+
+        let mut created = vec![];
+        for (line, result) in lines
+            .into_iter()
+            .zip(self.create_cards(cards, user_id).await?)
+        {
+            match result {
+                Ok(card) => created.push(card.card_id),
+                Err(error) => errors.push(ImportError {
+                    line,
+                    error: error.code(),
+                }),
+            }
+        }
+        errors.sort_by_key(|error| error.line);
+
+        Ok(ImportReport { created, errors })
+    }
+
+    /// Exports deck as delimited rows of front and back face data.
+    pub async fn export_deck(
+        &self,
+        deck_id: i64,
+        query: ExportQuery,
+        user_id: i64,
+    ) -> Result<String, CardError> {
+        sqlx::query_scalar!(
+            "SELECT 1 FROM decks WHERE id = $1 AND owner_id = $2",
+            deck_id,
+            user_id
+        )
+        .fetch_optional(self.db.borrow())
+        .await?
+        .ok_or(CardError::DeckNotFound)?;
+
+        let cards = sqlx::query!(
+            r#"
+                SELECT
+                    front.data AS front,
+                    ARRAY(
+                        SELECT COALESCE(faces.data, '')
+                        FROM unnest(cards.back) WITH ORDINALITY AS back(id, ord)
+                        JOIN faces ON faces.id = back.id
+                        ORDER BY back.ord
+                    ) AS "back!"
+                FROM cards
+                JOIN faces AS front ON front.id = cards.front
+                WHERE cards.deck_id = $1 AND cards.owner_id = $2
+                ORDER BY cards.id
+            "#,
+            deck_id,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await?;
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(query.format.delimiter())
+            .flexible(true)
+            .from_writer(vec![]);
+        for card in cards {
+            let front = card.front.unwrap_or_default();
+            // writing to a vector does not fail
+            let _ = writer.write_record([front].iter().chain(&card.back));
+        }
+
+        Ok(String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default())
+    }
+
+    /// Collects deck for an Anki package, rendering faces of cards with `render`.
+    pub async fn export_anki(
+        &self,
+        deck_id: i64,
+        query: AnkiExportQuery,
+        user_id: i64,
+        render: impl Fn(Option<i64>, Option<&str>) -> String,
+    ) -> Result<AnkiExport, CardError> {
+        let deck = sqlx::query!(
+            "SELECT name, description FROM decks WHERE id = $1 AND owner_id = $2",
+            deck_id,
+            user_id
+        )
+        .fetch_optional(self.db.borrow())
+        .await?
+        .ok_or(CardError::DeckNotFound)?;
+
+        let cards = sqlx::query!(
+            r#"
+                SELECT
+                    cards.id, cards.front, cards.back, cards.ease, cards.repetitions,
+                    cards.lapses,
+                    EXTRACT(EPOCH FROM card_schedules.due_at::timestamptz)::bigint AS due,
+                    CASE
+                        WHEN decks.scheduler = 'sm2' THEN cards.interval_days
+                        ELSE EXTRACT(EPOCH FROM decks.interval)::float8 / 86400
+                    END AS "interval_days!"
+                FROM cards
+                JOIN decks ON decks.id = cards.deck_id
+                JOIN card_schedules ON card_schedules.id = cards.id
+                WHERE cards.deck_id = $1 AND cards.owner_id = $2
+                ORDER BY cards.id
+            "#,
+            deck_id,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await?;
+
+        let face_ids = cards
+            .iter()
+            .flat_map(|card| [card.front].into_iter().chain(card.back.iter().copied()))
+            .collect::<Vec<_>>();
+        let faces = sqlx::query_as!(
+            Face,
+            "SELECT * FROM faces WHERE id = ANY($1) AND owner_id = $2",
+            &face_ids,
+            user_id
+        )
+        .fetch_all(self.db.borrow())
+        .await?
+        .into_iter()
+        .map(|face| (face.id, face))
+        .collect::<BTreeMap<_, _>>();
+        let render_face = |id: &i64| {
+            faces
+                .get(id)
+                .map(|face| render(face.extension_id, face.data.as_deref()))
+                .unwrap_or_default()
+        };
+
+        let mut revlogs = BTreeMap::<i64, Vec<AnkiRevlog>>::new();
+        if query.reviews {
+            let card_ids = cards.iter().map(|card| card.id).collect::<Vec<_>>();
+            let reviews = sqlx::query!(
+                r#"
+                    SELECT
+                        card_id,
+                        (EXTRACT(EPOCH FROM reviewed_at::timestamptz) * 1000)::bigint AS "time!",
+                        CASE grade
+                            WHEN 'again' THEN 1 WHEN 'hard' THEN 2 WHEN 'good' THEN 3 ELSE 4
+                        END AS "button!",
+                        next_interval_days, previous_interval_days, previous_ease, elapsed_ms,
+                        previous_done_at IS NULL AS "learning!"
+                    FROM reviews
+                    WHERE card_id = ANY($1) AND owner_id = $2 AND grade IS NOT NULL
+                    ORDER BY reviewed_at, id
+                "#,
+                &card_ids,
+                user_id
+            )
+            .fetch_all(self.db.borrow())
+            .await?;
+
+            for review in reviews {
+                revlogs.entry(review.card_id).or_default().push(AnkiRevlog {
+                    time: review.time,
+                    button: review.button.into(),
+                    interval_days: review.next_interval_days,
+                    last_interval_days: review.previous_interval_days,
+                    ease: review.previous_ease,
+                    elapsed_ms: review.elapsed_ms.unwrap_or(0).into(),
+                    learning: review.learning,
+                });
+            }
+        }
+
+        Ok(AnkiExport {
+            deck_id,
+            name: deck.name,
+            description: deck.description.unwrap_or_default(),
+            cards: cards
+                .into_iter()
+                .map(|card| AnkiExportCard {
+                    id: card.id,
+                    front: render_face(&card.front),
+                    back: card
+                        .back
+                        .iter()
+                        .map(render_face)
+                        .collect::<Vec<_>>()
+                        .join("<br>"),
+                    review: card.due.map(|due| AnkiReview {
+                        due,
+                        interval_days: card.interval_days,
+                        ease: card.ease,
+                        repetitions: card.repetitions,
+                        lapses: card.lapses,
+                    }),
+                    revlog: revlogs.remove(&card.id).unwrap_or_default(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(format: TableFormat, header: bool, front_column: usize) -> ImportQuery {
+        ImportQuery {
+            format,
+            header,
+            front_column,
+            front_extension: 0,
+            back_extension: 1,
+        }
+    }
+
+    fn faces(card: &CreateCard) -> (&str, Vec<&str>) {
+        (
+            card.front.1.as_deref().unwrap(),
+            card.back
+                .iter()
+                .map(|(_, data)| data.as_deref().unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parse_csv_rows() {
+        let text = "word,meaning,example\n apple , a fruit ,\n\"pear, green\",another fruit,\"a \"\"pear\"\"\"\n";
+        let rows = ImportRows::parse(7, text, &query(TableFormat::Csv, true, 0));
+
+        assert!(rows.errors.is_empty());
+        assert_eq!(rows.lines, [2, 3]);
+        assert_eq!(rows.cards[0].deck_id, 7);
+        assert_eq!(rows.cards[0].front.0, 0);
+        assert_eq!(rows.cards[0].back[0].0, 1);
+        assert_eq!(faces(&rows.cards[0]), ("apple", vec!["a fruit"]));
+        assert_eq!(
+            faces(&rows.cards[1]),
+            ("pear, green", vec!["another fruit", "a \"pear\""])
+        );
+    }
+
+    #[test]
+    fn parse_tsv_rows_with_front_column() {
+        let text = "a fruit\tapple\nmissing\n";
+        let rows = ImportRows::parse(1, text, &query(TableFormat::Tsv, false, 1));
+
+        assert_eq!(rows.lines, [1]);
+        assert_eq!(faces(&rows.cards[0]), ("apple", vec!["a fruit"]));
+        assert_eq!(rows.errors.len(), 1);
+        assert_eq!(rows.errors[0].line, 2);
+        assert_eq!(rows.errors[0].error, "missing_front");
+    }
+
+    #[test]
+    fn parse_stops_at_bulk_limit() {
+        let text = (0..BULK_LIMIT + 2)
+            .map(|i| format!("word{i},meaning\n"))
+            .collect::<String>();
+        let rows = ImportRows::parse(1, &text, &query(TableFormat::Csv, false, 0));
+
+        assert_eq!(rows.cards.len(), BULK_LIMIT);
+        assert_eq!(rows.errors.len(), 1);
+        assert_eq!(rows.errors[0].line, BULK_LIMIT as u64 + 1);
+        assert_eq!(rows.errors[0].error, "too_many_rows");
+    }
+}
@@ -0,0 +1,52 @@
+/// Implements `Display`, [`std::error::Error`], `code()` and `status()` of an error enum from
+/// the status, machine readable code and message of its variants. Errors respond with their
+/// status and `{"error": code}`, unless `custom_response` is given.
+macro_rules! api_error {
+    ($error:ident { $($variant:pat => ($status:ident, $code:literal, $message:expr)),* $(,)? }) => {
+        api_error!($error custom_response { $($variant => ($status, $code, $message)),* });
+
+        impl axum::response::IntoResponse for $error {
+            fn into_response(self) -> axum::response::Response {
+                (
+                    self.status(),
+                    axum::Json(serde_json::json!({ "error": self.code() })),
+                )
+                    .into_response()
+            }
+        }
+    };
+    (
+        $error:ident custom_response {
+            $($variant:pat => ($status:ident, $code:literal, $message:expr)),* $(,)?
+        }
+    ) => {
+        impl std::error::Error for $error {}
+
+        impl std::fmt::Display for $error {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($variant => std::fmt::Display::fmt(&$message, f),)*
+                }
+            }
+        }
+
+        #[allow(unused_variables)]
+        impl $error {
+            /// Machine readable error code.
+            pub fn code(&self) -> &'static str {
+                match self {
+                    $($variant => $code,)*
+                }
+            }
+
+            /// Status of responses with the error.
+            pub fn status(&self) -> axum::http::StatusCode {
+                match self {
+                    $($variant => axum::http::StatusCode::$status,)*
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use api_error;
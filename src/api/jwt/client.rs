@@ -1,9 +1,15 @@
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use super::KeyRing;
+
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
 
 /// Kino JWT manager for creating and validating JWT's.
 pub struct KinoClient {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    keys: KeyRing,
 }
 
 pub use super::KinoIdToken;
@@ -15,23 +21,54 @@ impl KinoClient {
         Self {
             encoding_key: EncodingKey::from_secret(secret),
             decoding_key: DecodingKey::from_secret(secret),
+            keys: KeyRing::default(),
         }
     }
 
+    /// Signs tokens with asymmetric keys. HS256 signs tokens until a key is active and
+    /// tokens without `kid` are verified with the secret until they have expired.
+    pub fn with_keys(mut self, keys: KeyRing) -> Self {
+        self.keys = keys;
+        self
+    }
+
     /// Encodes [`KinoIdToken`] to `jwt`
     pub fn encode(&self, payload: KinoIdToken) -> Option<String> {
+        if let Some(key) = self.keys.signing_key(chrono::Utc::now().timestamp()) {
+            let mut header = Header::new(key.algorithm);
+            header.kid = Some(key.kid.clone());
+            return encode(&header, &payload, &key.encoding_key).ok();
+        }
+
         encode(&Header::default(), &payload, &self.encoding_key).ok()
     }
 
     /// Decodes [`KinoIdToken`] from `jwt`
     pub fn decode(&self, payload: &str) -> Option<KinoIdToken> {
-        let mut validation = Validation::new(Algorithm::HS256);
+        // algorithm is decided by key, never by the token
+        let (algorithm, decoding_key) = match decode_header(payload).ok()?.kid {
+            Some(kid) => {
+                let key = self.keys.get(&kid)?;
+                (key.algorithm, &key.decoding_key)
+            }
+            None if self.keys.accepts_secret(chrono::Utc::now().timestamp()) => {
+                (Algorithm::HS256, &self.decoding_key)
+            }
+            None => return None,
+        };
+
+        let mut validation = Validation::new(algorithm);
         validation.validate_exp = true;
 
-        if let Ok(token) = decode::<KinoIdToken>(payload, &self.decoding_key, &validation) {
+        if let Ok(token) = decode::<KinoIdToken>(payload, decoding_key, &validation) {
             Some(token.claims)
         } else {
             None
         }
     }
+
+    /// Public keys to verify tokens.
+    pub fn jwks(&self) -> JwkSet {
+        self.keys.jwks()
+    }
 }
@@ -0,0 +1,280 @@
+use super::AUTH_TOKEN_LIFETIME;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use chrono::{DateTime, Utc};
+
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+
+use ring::{
+    rsa::PublicKeyComponents,
+    signature::{Ed25519KeyPair, KeyPair, RsaKeyPair},
+};
+
+use serde::Deserialize;
+
+use std::{fmt, fs, path::PathBuf};
+
+/// Entry of the key manifest, a JSON array of keys.
+#[derive(Debug, Deserialize)]
+pub struct KeyConfig {
+    pub kid: String,
+    /// `RS256` or `EdDSA`.
+    pub algorithm: Algorithm,
+    /// PKCS#8 PEM file of the private key. RSA keys may also be PKCS#1.
+    pub private_key: PathBuf,
+    /// Time the key starts signing tokens. Keys are published before, so that verifiers can
+    /// fetch them ahead of rotation.
+    pub active_from: DateTime<Utc>,
+}
+
+/// Key loading error.
+#[derive(Debug)]
+pub enum KeyError {
+    Io(std::io::Error),
+    InvalidManifest(serde_json::Error),
+    InvalidKey(String),
+    UnsupportedAlgorithm(String),
+    DuplicateKid(String),
+}
+
+impl std::error::Error for KeyError {}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            Self::InvalidManifest(error) => error.fmt(f),
+            Self::InvalidKey(kid) => write!(f, "Key {kid} is not a valid private key."),
+            Self::UnsupportedAlgorithm(kid) => write!(f, "Key {kid} has unsupported algorithm."),
+            Self::DuplicateKid(kid) => write!(f, "Key id {kid} is used more than once."),
+        }
+    }
+}
+
+pub(super) struct SigningKey {
+    pub(super) kid: String,
+    pub(super) algorithm: Algorithm,
+    pub(super) encoding_key: EncodingKey,
+    pub(super) decoding_key: DecodingKey,
+    active_from: i64,
+    jwk: Jwk,
+}
+
+impl SigningKey {
+    fn load(config: KeyConfig) -> Result<Self, KeyError> {
+        let invalid = || KeyError::InvalidKey(config.kid.clone());
+
+        let pem_bytes = fs::read(&config.private_key).map_err(KeyError::Io)?;
+        let pem = pem::parse(&pem_bytes).map_err(|_| invalid())?;
+
+        let (encoding_key, key_algorithm, parameters) = match config.algorithm {
+            Algorithm::RS256 => {
+                let key_pair = match pem.tag() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(pem.contents()),
+                    _ => RsaKeyPair::from_pkcs8(pem.contents()),
+                }
+                .map_err(|_| invalid())?;
+                let public = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+
+                (
+                    EncodingKey::from_rsa_pem(&pem_bytes).map_err(|_| invalid())?,
+                    KeyAlgorithm::RS256,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        n: URL_SAFE_NO_PAD.encode(public.n),
+                        e: URL_SAFE_NO_PAD.encode(public.e),
+                        ..Default::default()
+                    }),
+                )
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pem.contents())
+                    .map_err(|_| invalid())?;
+
+                (
+                    EncodingKey::from_ed_pem(&pem_bytes).map_err(|_| invalid())?,
+                    KeyAlgorithm::EdDSA,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(key_pair.public_key()),
+                    }),
+                )
+            }
+            _ => return Err(KeyError::UnsupportedAlgorithm(config.kid)),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(config.kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        Ok(Self {
+            decoding_key: DecodingKey::from_jwk(&jwk).map_err(|_| invalid())?,
+            kid: config.kid,
+            algorithm: config.algorithm,
+            encoding_key,
+            active_from: config.active_from.timestamp(),
+            jwk,
+        })
+    }
+}
+
+/// Asymmetric keys identified by `kid`. The latest active key signs tokens, all keys verify
+/// them, so that tokens signed by a retired key are valid until they expire.
+#[derive(Default)]
+pub struct KeyRing {
+    keys: Vec<SigningKey>,
+}
+
+impl KeyRing {
+    /// Loads keys listed in a JSON manifest of [`KeyConfig`]'s.
+    pub fn load(manifest: &str) -> Result<Self, KeyError> {
+        let manifest = fs::read(manifest).map_err(KeyError::Io)?;
+        let configs: Vec<KeyConfig> =
+            serde_json::from_slice(&manifest).map_err(KeyError::InvalidManifest)?;
+
+        let mut keys = Vec::with_capacity(configs.len());
+        for config in configs {
+            if keys.iter().any(|key: &SigningKey| key.kid == config.kid) {
+                return Err(KeyError::DuplicateKid(config.kid));
+            }
+            keys.push(SigningKey::load(config)?);
+        }
+        keys.sort_by_key(|key| key.active_from);
+
+        Ok(Self { keys })
+    }
+
+    /// Key that signs tokens at `now`.
+    pub(super) fn signing_key(&self, now: i64) -> Option<&SigningKey> {
+        self.keys.iter().rev().find(|key| key.active_from <= now)
+    }
+
+    /// Whether HS256 tokens are verified at `now`. The secret stops signing once the first key
+    /// is active, so its tokens are rejected after they could have expired.
+    pub(super) fn accepts_secret(&self, now: i64) -> bool {
+        self.keys
+            .first()
+            .is_none_or(|key| now < key.active_from + AUTH_TOKEN_LIFETIME as i64)
+    }
+
+    pub(super) fn get(&self, kid: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    /// Public keys in JWKS format.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ring::rand::SystemRandom;
+
+    // Writes generated Ed25519 keys and their manifest to a temporary directory.
+    fn manifest(name: &str, keys: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kino-keys-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut configs = vec![];
+        for (kid, active_from) in keys {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let path = dir.join(format!("{kid}.pem"));
+            fs::write(
+                &path,
+                pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref())),
+            )
+            .unwrap();
+            configs.push(serde_json::json!({
+                "kid": kid,
+                "algorithm": "EdDSA",
+                "private_key": path,
+                "active_from": active_from,
+            }));
+        }
+
+        let manifest = dir.join("keys.json");
+        fs::write(&manifest, serde_json::to_vec(&configs).unwrap()).unwrap();
+        manifest
+    }
+
+    fn timestamp(time: &str) -> i64 {
+        time.parse::<DateTime<Utc>>().unwrap().timestamp()
+    }
+
+    #[test]
+    fn secret_is_accepted_without_keys() {
+        assert!(KeyRing::default().accepts_secret(i64::MAX - AUTH_TOKEN_LIFETIME as i64));
+    }
+
+    #[test]
+    fn secret_is_accepted_until_its_tokens_expire() {
+        let manifest = manifest(
+            "secret",
+            &[
+                ("new", "2024-02-01T00:00:00Z"),
+                ("old", "2024-01-01T00:00:00Z"),
+            ],
+        );
+        let ring = KeyRing::load(manifest.to_str().unwrap()).unwrap();
+
+        // first key to become active ends the secret
+        let first = timestamp("2024-01-01T00:00:00Z");
+        assert!(ring.accepts_secret(first - 1));
+        assert!(ring.accepts_secret(first + AUTH_TOKEN_LIFETIME as i64 - 1));
+        assert!(!ring.accepts_secret(first + AUTH_TOKEN_LIFETIME as i64));
+    }
+
+    #[test]
+    fn latest_active_key_signs() {
+        let manifest = manifest(
+            "signing",
+            &[
+                ("new", "2024-02-01T00:00:00Z"),
+                ("old", "2024-01-01T00:00:00Z"),
+            ],
+        );
+        let ring = KeyRing::load(manifest.to_str().unwrap()).unwrap();
+
+        let signing_kid = |time| ring.signing_key(timestamp(time)).map(|key| &key.kid[..]);
+        assert_eq!(signing_kid("2023-12-31T23:59:59Z"), None);
+        assert_eq!(signing_kid("2024-01-15T00:00:00Z"), Some("old"));
+        assert_eq!(signing_kid("2024-02-01T00:00:00Z"), Some("new"));
+
+        // retired keys still verify
+        assert!(ring.get("old").is_some());
+        assert_eq!(ring.jwks().keys.len(), 2);
+    }
+
+    #[test]
+    fn duplicate_kids_are_rejected() {
+        let manifest = manifest(
+            "duplicate",
+            &[
+                ("key", "2024-01-01T00:00:00Z"),
+                ("key", "2024-02-01T00:00:00Z"),
+            ],
+        );
+        assert!(matches!(
+            KeyRing::load(manifest.to_str().unwrap()),
+            Err(KeyError::DuplicateKid(kid)) if kid == "key"
+        ));
+    }
+}
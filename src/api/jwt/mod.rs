@@ -1,5 +1,9 @@
 mod client;
+mod keys;
 mod payload;
 
 pub use client::KinoClient;
-pub use payload::{KinoIdToken, KinoTokenScope};
+pub use keys::KeyRing;
+pub use payload::{
+    expires_after, KinoIdToken, KinoTokenScope, AUTH_TOKEN_LIFETIME, DELETE_TOKEN_LIFETIME,
+};
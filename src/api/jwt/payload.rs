@@ -1,20 +1,43 @@
 use serde::{Deserialize, Serialize};
 
-/// Kino JWT claims.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use std::time::{Duration, SystemTime};
+
+/// Lifetime of access tokens in seconds. Clients renew them with refresh tokens.
+pub const AUTH_TOKEN_LIFETIME: u64 = 900;
+
+/// Lifetime of account deletion tokens in seconds.
+pub const DELETE_TOKEN_LIFETIME: u64 = 300;
+
+/// Kino JWT claims. `jti` and `gen` are set when the token is issued.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct KinoIdToken {
     pub sub: i64,
     pub scope: Vec<KinoTokenScope>,
-    pub google_id: String,
     pub email: String,
     pub username: Option<String>,
     pub exp: u64,
+    /// Unique id of the token to revoke it.
+    pub jti: i64,
+    /// Session the token was issued for.
+    pub sid: i64,
+    /// Token generation of user when issued, tokens of older generations are revoked.
+    pub gen: u64,
 }
 
 /// Scopes to that token capable to do.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum KinoTokenScope {
     Auth,
-    // TODO: DeleteAccount,
+    /// Confirms account deletion, cannot be used to authenticate.
+    DeleteAccount,
     // TODO: ChangeEmail,
 }
+
+/// Expiry of a token issued now.
+pub fn expires_after(lifetime: u64) -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+        + lifetime
+}
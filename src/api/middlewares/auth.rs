@@ -1,7 +1,14 @@
-use crate::api::{jwt::KinoTokenScope, Server};
+use crate::api::{
+    database::Orm,
+    jwt::{expires_after, KinoIdToken, KinoTokenScope, AUTH_TOKEN_LIFETIME},
+    Server,
+};
 
 use std::sync::Arc;
 
+/// Seconds between last seen updates of a session.
+const LAST_SEEN_INTERVAL: u64 = 300;
+
 use axum::{
     extract::Request,
     http::{HeaderValue, StatusCode},
@@ -10,6 +17,8 @@ use axum::{
     Extension, Router,
 };
 
+use redis::Commands;
+
 async fn auth(
     Extension(server): Extension<Arc<Server>>,
     mut request: Request,
@@ -24,13 +33,18 @@ async fn auth(
     };
 
     if let Some(kino_token) = server.kino_client.decode(token) {
-        if kino_token.scope.contains(&KinoTokenScope::Auth) {
+        if kino_token.scope.contains(&KinoTokenScope::Auth) && !server.is_revoked(&kino_token) {
             tracing::debug!(
                 "User authenticated: id={} email={}",
                 kino_token.sub,
                 kino_token.email
             );
 
+            if server.should_touch_session(kino_token.sid) {
+                let orm = Orm::new(Arc::clone(&server.pg), Arc::clone(&server.snowflake));
+                tokio::spawn(async move { orm.touch_session(kino_token.sid).await });
+            }
+
             request
                 .headers_mut()
                 .insert("UserId", HeaderValue::from(kino_token.sub));
@@ -45,6 +59,82 @@ async fn auth(
 }
 
 impl Server {
+    /// Encodes token with a new `jti` and the current generation of its user.
+    pub(crate) fn issue_token(&self, token: KinoIdToken) -> Option<String> {
+        let gen = self
+            .redis
+            .lock()
+            .unwrap()
+            .get::<_, Option<u64>>(format!("token_gen:{}", token.sub))
+            .ok()?
+            .unwrap_or(0);
+
+        self.kino_client.encode(KinoIdToken {
+            jti: self.snowflake.gen_id(),
+            gen,
+            ..token
+        })
+    }
+
+    /// Rejects a single token until it expires.
+    pub(crate) fn revoke_token(&self, token: &KinoIdToken) -> bool {
+        let ttl = token.exp.saturating_sub(expires_after(0)).max(1);
+        self.redis
+            .lock()
+            .unwrap()
+            .set_ex::<_, _, ()>(format!("revoked:{}", token.jti), 1, ttl)
+            .is_ok()
+    }
+
+    /// Rejects all tokens of user issued so far by advancing the user's token generation.
+    pub(crate) fn revoke_user(&self, user_id: i64) -> bool {
+        self.redis
+            .lock()
+            .unwrap()
+            .incr::<_, _, u64>(format!("token_gen:{user_id}"), 1)
+            .is_ok()
+    }
+
+    /// Rejects tokens of a session. Its refresh tokens are expected to be deleted, so that the
+    /// mark only needs to outlive access tokens.
+    pub(crate) fn revoke_session(&self, session_id: i64) -> bool {
+        self.redis
+            .lock()
+            .unwrap()
+            .set_ex::<_, _, ()>(
+                format!("revoked_session:{session_id}"),
+                1,
+                AUTH_TOKEN_LIFETIME,
+            )
+            .is_ok()
+    }
+
+    /// Tokens are treated as revoked while revocations cannot be looked up.
+    pub(crate) fn is_revoked(&self, token: &KinoIdToken) -> bool {
+        let Ok((revoked, session_revoked, gen)) = redis::pipe()
+            .exists(format!("revoked:{}", token.jti))
+            .exists(format!("revoked_session:{}", token.sid))
+            .get(format!("token_gen:{}", token.sub))
+            .query::<(bool, bool, Option<u64>)>(&mut self.redis.lock().unwrap())
+        else {
+            return true;
+        };
+
+        revoked || session_revoked || gen.is_some_and(|gen| token.gen < gen)
+    }
+
+    // Redis throttles last seen updates, so that Postgres is written once per interval.
+    fn should_touch_session(&self, session_id: i64) -> bool {
+        redis::cmd("SET")
+            .arg(format!("seen:{session_id}"))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(LAST_SEEN_INTERVAL)
+            .query::<Option<String>>(&mut self.redis.lock().unwrap())
+            .is_ok_and(|set| set.is_some())
+    }
+
     /// Authentication with `Token` header.
     pub(crate) fn auth(self: &Arc<Self>, router: Router) -> Router {
         router
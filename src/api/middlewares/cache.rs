@@ -0,0 +1,64 @@
+use crate::api::Server;
+
+use std::sync::Arc;
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Extension, Router,
+};
+
+/// Clients may use dictionary responses for a week without revalidating.
+const CACHE_CONTROL: &str = "public, max-age=604800";
+
+async fn cache(
+    Extension(server): Extension<Arc<Server>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // dictionary responses only depend on url and loaded dictionary
+    let etag = format!("\"{}\"", server.wordnet.version());
+
+    let matches = request
+        .headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        });
+
+    let mut response = if matches {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let response = next.run(request).await;
+        if !response.status().is_success() {
+            return response;
+        }
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(CACHE_CONTROL),
+    );
+
+    response
+}
+
+impl Server {
+    /// Adds `ETag` and `Cache-Control` headers keyed by dictionary version. Requests with a
+    /// matching `If-None-Match` header are answered with `304 Not Modified` without running
+    /// inner layers, so that routes should be limited inside this layer.
+    pub(crate) fn cache(self: &Arc<Self>, router: Router) -> Router {
+        router
+            .layer(middleware::from_fn(cache))
+            .layer(Extension(Arc::clone(self)))
+    }
+}
@@ -30,33 +30,11 @@ macro_rules! limit_middleware_fn {
 
                 let key = format!("{}:{}:{ip}", stringify!($fn), limiter_options.limiter_id);
 
-                let redis = limiter_options.database;
-                let rate_limit: i32 = redis::transaction(&mut redis.lock().unwrap(), &[&key[..]], |con, pipe| {
-                    let key = &key[..];
-                    let limit: Option<usize> = con.get(key)?;
-                    // increment limit by one or signal limit exceeded if exists
-                    if let Some(limit) = limit {
-                        if limit >= limiter_options.num {
-                            let ttl = con.ttl::<_, Option<i32>>(key)?;
-                            return Ok(ttl)
-                        }
-                        con.incr::<_, i32, String>(key, 1)?;
-                    } else {
-                        pipe
-                            .set(key, 1).ignore()
-                            .expire(key, limiter_options.per.as_secs().try_into().unwrap()).ignore()
-                            .query::<()>(con)?;
-                    }
-                    Ok(Some(-2))
-                }).ok().unwrap_or(-2);
-
+                let rate_limit = consume(&limiter_options.database, &key, 1, limiter_options.num, limiter_options.per);
 
                 if rate_limit > 0 {
-                    let mut response = (StatusCode::TOO_MANY_REQUESTS, rate_limit.to_string()).into_response();
-                    response.headers_mut().insert("Retry-After", HeaderValue::from_str(&rate_limit.to_string()[..]).unwrap());
-                    response.headers_mut().insert("Content-Type", HeaderValue::from_static("application/json"));
                     tracing::debug!("Rate limit exceeded: key={} num={} per={:?}", key, limiter_options.num, limiter_options.per);
-                    response
+                    too_many_requests(rate_limit)
                 } else {
                     let response = next.run(request).await;
                     response
@@ -89,6 +67,66 @@ macro_rules! limit_middleware_fn {
     }
 }
 
+// Consumes `cost` from the limit of key. Returns remaining seconds if limit exceeded, negative
+// otherwise.
+fn consume(redis: &Mutex<RedisClient>, key: &str, cost: usize, num: usize, per: Duration) -> i32 {
+    redis::transaction(&mut redis.lock().unwrap(), &[key], |con, pipe| {
+        let limit: Option<usize> = con.get(key)?;
+        // increment limit by cost or signal limit exceeded if exists
+        if let Some(limit) = limit {
+            if limit + cost > num {
+                let ttl = con.ttl::<_, Option<i32>>(key)?;
+                return Ok(ttl);
+            }
+            con.incr::<_, usize, String>(key, cost)?;
+        } else {
+            pipe.set(key, cost)
+                .ignore()
+                .expire(key, per.as_secs().try_into().unwrap())
+                .ignore()
+                .query::<()>(con)?;
+        }
+        Ok(Some(-2))
+    })
+    .ok()
+    .unwrap_or(-2)
+}
+
+fn too_many_requests(rate_limit: i32) -> Response {
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, rate_limit.to_string()).into_response();
+    response.headers_mut().insert(
+        "Retry-After",
+        HeaderValue::from_str(&rate_limit.to_string()[..]).unwrap(),
+    );
+    response
+        .headers_mut()
+        .insert("Content-Type", HeaderValue::from_static("application/json"));
+    response
+}
+
+impl Server {
+    /// Consumes `cost` from a per-user limit in handlers whose cost is known after parsing
+    /// the request. Returns the response to send if limit exceeded.
+    pub(crate) fn limit_user_cost(
+        &self,
+        name: &str,
+        user_id: i64,
+        cost: usize,
+        num: usize,
+        per: Duration,
+    ) -> Option<Response> {
+        let key = format!("cost:{name}:{user_id}");
+
+        let rate_limit = consume(&self.redis, &key, cost, num, per);
+        if rate_limit > 0 {
+            tracing::debug!("Rate limit exceeded: key={key} cost={cost} num={num} per={per:?}");
+            Some(too_many_requests(rate_limit))
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(super) struct LimitOptions {
     num: usize,
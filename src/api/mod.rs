@@ -1,3 +1,6 @@
+/// Error responses.
+mod error;
+
 /// Structs for building and running a server.
 mod server;
 pub use server::{Server, ServerBuilder};
@@ -19,3 +22,15 @@ mod middlewares;
 
 /// Database query structs.
 mod database;
+
+/// Multiple-choice quiz generation from cards.
+mod quiz;
+
+/// Dictionary bundles for offline clients.
+mod bundles;
+
+/// Spaced repetition scheduling algorithms.
+mod scheduler;
+
+/// Anki package reading.
+mod anki;
@@ -0,0 +1,283 @@
+use crate::{
+    api::database::ReviewResponse,
+    dicts::{Database, SynsetId, WordNetDatabase},
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+
+/// Extension ids of faces whose data is a dictionary word.
+pub const WORD_EXTENSIONS: [i64; 2] = [0, 2];
+
+/// Kind of a multiple-choice question.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    /// Prompt is a definition, options are words.
+    DefinitionToWord,
+    /// Prompt is the word, options are definitions.
+    WordToDefinition,
+    /// Prompt is the word, options are words and only one of them is a synonym.
+    Synonym,
+}
+
+/// Multiple-choice question about a card.
+#[derive(Debug, Serialize)]
+pub struct Question {
+    /// Answers refer to the question by id.
+    pub id: i64,
+    pub card_id: i64,
+    pub kind: QuestionKind,
+    pub prompt: String,
+    pub options: Vec<String>,
+    #[serde(skip)]
+    pub answer: String,
+}
+
+/// Issued [`Question`] kept on server until it is answered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingQuestion {
+    pub card_id: i64,
+    pub kind: QuestionKind,
+    pub answer: String,
+}
+
+impl From<&Question> for PendingQuestion {
+    fn from(question: &Question) -> Self {
+        Self {
+            card_id: question.card_id,
+            kind: question.kind,
+            answer: question.answer.clone(),
+        }
+    }
+}
+
+/// Answer of a [`Question`].
+#[derive(Debug, Deserialize)]
+pub struct Answer {
+    pub question_id: i64,
+    pub answer: String,
+}
+
+/// Card that has a dictionary word on its front face.
+#[derive(Debug)]
+pub struct QuizCard {
+    pub id: i64,
+    pub word: String,
+}
+
+/// Result of grading an [`Answer`] with the card's state after it is reviewed as graded.
+#[derive(Debug, Serialize)]
+pub struct Grade {
+    pub correct: bool,
+    #[serde(flatten)]
+    pub review: ReviewResponse,
+}
+
+/// Multiple-choice quiz generator using WordNet glosses.
+pub struct Quiz<'a> {
+    wordnet: &'a WordNetDatabase,
+    state: u64,
+}
+
+impl<'a> Quiz<'a> {
+    /// Number of options in a question.
+    pub const OPTION_COUNT: usize = 4;
+    /// Maximum number of questions in a quiz.
+    pub const MAX_QUESTIONS: usize = 20;
+
+    /// Creates a [`Quiz`], same seed generates same questions.
+    pub fn new(wordnet: &'a WordNetDatabase, seed: u64) -> Self {
+        Self {
+            wordnet,
+            // xorshift state must not be zero
+            state: seed | 1,
+        }
+    }
+
+    /// Converts face data to WordNet lemma form.
+    pub fn normalize(word: &str) -> String {
+        word.trim()
+            .to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    /// Builds questions from cards with ids from `new_id`. Cards that are not found in WordNet
+    /// are skipped.
+    pub fn questions(
+        &mut self,
+        cards: &[QuizCard],
+        mut new_id: impl FnMut() -> i64,
+    ) -> Vec<Question> {
+        let mut cards = cards.iter().collect::<Vec<_>>();
+        self.shuffle(&mut cards);
+
+        let deck_words = cards
+            .iter()
+            .map(|card| Self::normalize(&card.word))
+            .collect::<Vec<_>>();
+
+        let mut questions = Vec::new();
+        for (card, lemma) in cards.iter().zip(&deck_words) {
+            if questions.len() == Self::MAX_QUESTIONS {
+                break;
+            }
+
+            if let Some(question) = self.question(new_id(), card.id, lemma, &deck_words) {
+                questions.push(question)
+            }
+        }
+
+        questions
+    }
+
+    /// Checks whether answer is the correct option of the issued question.
+    pub fn grade(question: &PendingQuestion, answer: &str) -> bool {
+        match question.kind {
+            QuestionKind::WordToDefinition => answer.trim() == question.answer,
+            QuestionKind::DefinitionToWord | QuestionKind::Synonym => {
+                Self::normalize(answer) == Self::normalize(&question.answer)
+            }
+        }
+    }
+
+    fn question(
+        &mut self,
+        id: i64,
+        card_id: i64,
+        lemma: &str,
+        deck_words: &[String],
+    ) -> Option<Question> {
+        let synsets = self.wordnet.synsets(lemma);
+        let sense = *synsets.first()?;
+        let meanings = self.wordnet.synset(sense)?.meanings();
+
+        let synonyms = self.synonyms(&synsets, lemma);
+        let distractors = self.distractors(sense, lemma, &synonyms, deck_words);
+
+        let mut kinds = vec![QuestionKind::DefinitionToWord];
+        let synonym = self.wordnet.lemmas(sense).into_iter().find_map(|word| {
+            let word = word.to_lowercase();
+            (word != lemma).then_some(word)
+        });
+        if synonym.is_some() {
+            kinds.push(QuestionKind::Synonym)
+        }
+        let definitions = distractors
+            .iter()
+            .filter_map(|word| {
+                let synset = *self.wordnet.synsets(word).first()?;
+                Some(self.wordnet.synset(synset)?.meanings().to_string())
+            })
+            .filter(|definition| definition != meanings)
+            .take(Self::OPTION_COUNT - 1)
+            .collect::<Vec<_>>();
+        if definitions.len() == Self::OPTION_COUNT - 1 {
+            kinds.push(QuestionKind::WordToDefinition)
+        }
+
+        let kind = kinds[self.next() as usize % kinds.len()];
+        let words = || distractors.iter().take(Self::OPTION_COUNT - 1).cloned();
+        let (prompt, answer, mut options) = match kind {
+            QuestionKind::DefinitionToWord => {
+                (meanings.to_string(), lemma.to_string(), words().collect())
+            }
+            QuestionKind::WordToDefinition => {
+                (lemma.to_string(), meanings.to_string(), definitions)
+            }
+            QuestionKind::Synonym => (lemma.to_string(), synonym?, words().collect()),
+        };
+
+        if options.len() < Self::OPTION_COUNT - 1 {
+            return None;
+        }
+        options.push(answer.clone());
+        self.shuffle(&mut options);
+
+        Some(Question {
+            id,
+            card_id,
+            kind,
+            prompt,
+            options,
+            answer,
+        })
+    }
+
+    // Lowercased words of all senses.
+    fn synonyms(&self, synsets: &[SynsetId], lemma: &str) -> BTreeSet<String> {
+        let mut synonyms = BTreeSet::from([lemma.to_string()]);
+        for &synset in synsets {
+            synonyms.extend(
+                self.wordnet
+                    .lemmas(synset)
+                    .into_iter()
+                    .map(|word| word.to_lowercase()),
+            );
+        }
+        synonyms
+    }
+
+    // Wrong options, semantically similar ones first.
+    fn distractors(
+        &mut self,
+        sense: SynsetId,
+        lemma: &str,
+        synonyms: &BTreeSet<String>,
+        deck_words: &[String],
+    ) -> Vec<String> {
+        let mut sister_terms = self
+            .wordnet
+            .sister_terms(sense)
+            .into_iter()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>();
+        self.shuffle(&mut sister_terms);
+
+        let mut deck_words = deck_words.to_vec();
+        self.shuffle(&mut deck_words);
+
+        let prefix = &lemma[..lemma.char_indices().nth(2).map_or(lemma.len(), |(i, _)| i)];
+        let similar_spelled = self
+            .wordnet
+            .suggest_search(prefix)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut distractors: Vec<String> = Vec::new();
+        for word in sister_terms
+            .into_iter()
+            .chain(deck_words)
+            .chain(similar_spelled)
+        {
+            if distractors.len() == Self::OPTION_COUNT * 2 {
+                break;
+            }
+            if !synonyms.contains(&word) && !distractors.contains(&word) {
+                distractors.push(word)
+            }
+        }
+
+        distractors
+    }
+
+    // Fisher-Yates shuffle.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.next() as usize % (i + 1);
+            items.swap(i, j);
+        }
+    }
+
+    // xorshift64 pseudo random numbers. Quizzes do not require a strong generator.
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
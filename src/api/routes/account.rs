@@ -0,0 +1,96 @@
+macro_rules! export {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use axum::{
+            http::{header, StatusCode},
+            response::IntoResponse,
+            Extension, Json,
+        };
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            match orm.export_account(user.sub).await {
+                Some(archive) => (
+                    [(
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"kino-{}.json\"", user.sub),
+                    )],
+                    Json(archive),
+                )
+                    .into_response(),
+                None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! import {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use database::AccountArchive;
+
+        use axum::{response::IntoResponse, Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>, Json(archive): Json<AccountArchive>| async move {
+            match orm.import_account(archive, user.sub).await {
+                Ok(report) => Json(report).into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! delete_token {
+    ($server:expr) => {{
+        let server = Arc::clone($server);
+
+        use crate::api::jwt::{expires_after, KinoTokenScope, DELETE_TOKEN_LIFETIME};
+
+        use axum::{Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            Json(server.issue_token(KinoIdToken {
+                scope: vec![KinoTokenScope::DeleteAccount],
+                exp: expires_after(DELETE_TOKEN_LIFETIME),
+                ..user
+            }))
+        }
+    }};
+}
+
+macro_rules! delete {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::jwt::KinoTokenScope;
+
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            response::IntoResponse,
+        };
+
+        move |headers: HeaderMap| async move {
+            let Some(token) = headers
+                .get("Token")
+                .and_then(|header| header.to_str().ok())
+                .and_then(|token| server.kino_client.decode(token))
+                .filter(|token| token.scope.contains(&KinoTokenScope::DeleteAccount))
+                .filter(|token| !server.is_revoked(token))
+            else {
+                return StatusCode::UNAUTHORIZED.into_response();
+            };
+
+            if orm.delete_account(token.sub).await.is_none() {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            server.revoke_user(token.sub);
+
+            tracing::debug!("User deleted: id={} email={}", token.sub, token.email);
+            StatusCode::NO_CONTENT.into_response()
+        }
+    }};
+}
+
+pub(crate) use {delete, delete_token, export, import};
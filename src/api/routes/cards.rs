@@ -0,0 +1,35 @@
+macro_rules! bulk {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use database::{BulkCardResult, CreateCard, BULK_LIMIT};
+
+        use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>, Json(cards): Json<Vec<CreateCard>>| async move {
+            if cards.is_empty() || cards.len() > BULK_LIMIT {
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+
+            if let Some(response) =
+                server.limit_user_cost("cards_bulk", user.sub, cards.len(), 2 * BULK_LIMIT, Duration::from_secs(600))
+            {
+                return response;
+            }
+
+            match orm.create_cards(cards, user.sub).await {
+                Ok(results) => Json(
+                    results
+                        .into_iter()
+                        .map(BulkCardResult::from)
+                        .collect::<Vec<_>>(),
+                )
+                .into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+pub(crate) use bulk;
@@ -0,0 +1,68 @@
+macro_rules! identities {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use axum::{Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            Json(orm.identities(user.sub).await)
+        }
+    }};
+}
+
+macro_rules! link_identity {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use axum::{
+            extract::{Path, RawQuery},
+            http::StatusCode,
+            response::IntoResponse,
+            Extension, Json,
+        };
+
+        move |Path(provider): Path<String>,
+              Extension(user): Extension<KinoIdToken>,
+              RawQuery(token): RawQuery| async move {
+            let Some(token) = token else {
+                return StatusCode::BAD_REQUEST.into_response();
+            };
+
+            let Ok(token) = server.oidc.validate(&provider, &token) else {
+                return StatusCode::UNAUTHORIZED.into_response();
+            };
+
+            // unverified emails are not kept
+            let email = token
+                .email
+                .as_deref()
+                .filter(|_| token.email_verified == Some(true));
+
+            match orm
+                .link_identity(user.sub, &token.iss, &token.sub, email)
+                .await
+            {
+                Ok(identity) => Json(identity).into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! unlink_identity {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension};
+
+        move |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| async move {
+            match orm.unlink_identity(id, user.sub).await {
+                Ok(()) => StatusCode::NO_CONTENT.into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+pub(crate) use {identities, link_identity, unlink_identity};
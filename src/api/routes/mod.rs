@@ -1,38 +1,82 @@
-/// Sign in route.
+/// Account data routes.
+mod account;
+/// Card routes.
+mod cards;
+/// Linked identity routes.
+mod identities;
+/// Quiz routes.
+mod quiz;
+/// Sign in routes.
 mod signin;
+/// Token refresh, logout and session routes.
+mod tokens;
+/// Import and export routes.
+mod transfer;
 /// Users routes.
 mod users;
+/// WordNet routes that do not fit in `dict!`.
+mod wordnet;
 
 use super::{
+    anki::PACKAGE_LIMIT,
     database::{self, BulkRequest, Orm},
     jwt::KinoIdToken,
     Server,
 };
 
-use std::{borrow::Borrow, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 
 use axum::{
-    extract::{Path, RawQuery},
+    extract::{DefaultBodyLimit, Path, Query, RawQuery},
     http::StatusCode,
     response::IntoResponse,
     routing, Extension, Json, Router,
 };
 
+use validator::Validate;
+
 impl Server {
     pub(crate) fn routes(self: &'static Arc<Self>) -> Router {
         let orm = Orm::new(Arc::clone(&self.pg), Arc::clone(&self.snowflake));
 
         let public = self.limit_ip(
-            Router::new().route("/signin", routing::get(signin::signin!(Arc::clone(self)))),
+            Router::new()
+                .route("/signin", routing::get(signin::signin!(self, "google")))
+                .route("/signin/:provider", routing::get(signin::signin!(self))),
             5,
             Duration::from_secs(5),
         );
 
+        let jwks = self.limit_ip(
+            Router::new().route(
+                "/.well-known/jwks.json",
+                routing::get(move || async move { Json(self.kino_client.jwks()) }),
+            ),
+            10,
+            Duration::from_secs(10),
+        );
+
+        let refresh = self.limit_ip(
+            Router::new().route("/token/refresh", routing::post(tokens::refresh!(self, orm))),
+            5,
+            Duration::from_secs(60),
+        );
+
+        // deletion tokens do not have `Auth` scope, so that they are checked in the handler
+        let account_deletion = self.limit_ip(
+            Router::new().route(
+                "/account/delete",
+                routing::post(account::delete!(self, orm)),
+            ),
+            1,
+            Duration::from_secs(10),
+        );
+
         macro_rules! routes {
             {
-                $($type:ident: $route:expr, ($num:expr, $per:expr), $fn:expr);* $(;)?
+                @limited $($type:ident: $route:expr, ($num:expr, $per:expr), $fn:expr);* $(;)?
             } => {
-               self.auth(Router::new()
+                Router::new()
                 $(
                     .merge(
                         self.limit_user(
@@ -40,7 +84,12 @@ impl Server {
                             $num, Duration::from_secs($per)
                         )
                     )
-                )*)
+                )*
+            };
+            {
+                $($type:ident: $route:expr, ($num:expr, $per:expr), $fn:expr);* $(;)?
+            } => {
+               self.auth(routes!(@limited $($type: $route, ($num, $per), $fn);*))
             };
         }
 
@@ -117,12 +166,28 @@ impl Server {
 
         let wn = dict!(self.wordnet);
 
-        let auth_required = routes! {
-            get: "/token_info", (5, 5), |Extension(kino_token): Extension<KinoIdToken>| async move { Json(kino_token) };
-            get: "/users", (5, 5), users::user!(Arc::clone(&self.pg));
+        // cache layer sits between auth and limiter, so that revalidation is not limited
+        let dictionary = self.auth(self.cache(routes! {
+            @limited
             get: "/wn/get", (5, 2), wn.0;
             get: "/wn/suggest", (3, 5), wn.1;
             get: "/wn/suggest_search", (10, 1), wn.2;
+        }));
+
+        let auth_required = routes! {
+            get: "/token_info", (5, 5), |Extension(kino_token): Extension<KinoIdToken>| async move { Json(kino_token) };
+            get: "/users", (5, 5), users::user!(Arc::clone(&self.pg));
+            post: "/logout", (5, 5), tokens::logout!(self, orm);
+            post: "/logout/all", (1, 10), tokens::logout_all!(self, orm);
+            get: "/sessions", (3, 5), tokens::sessions!(orm);
+            post: "/sessions/:id/revoke", (3, 5), tokens::revoke_session!(self, orm);
+            post: "/users/edit", (2, 10), users::edit!(self, orm);
+            get: "/identities", (3, 5), identities::identities!(orm);
+            post: "/identities/link/:provider", (2, 10), identities::link_identity!(self, orm);
+            post: "/identities/:id/unlink", (2, 10), identities::unlink_identity!(orm);
+            post: "/wn/batch", (2, 2), wordnet::batch!(self);
+            post: "/wn/analyze", (3, 5), wordnet::analyze!(self);
+            get: "/wn/bundle", (1, 60), wordnet::bundle!(self);
             post: "/bulk", (5, 5), {
                 let orm = orm.clone();
                 |Json(bulk_request): Json<BulkRequest>| {
@@ -139,76 +204,172 @@ impl Server {
                     }
                 }
             };
+            get: "/queue", (3, 5), {
+                let orm = orm.clone();
+                |Extension(user): Extension<KinoIdToken>, Query(query): Query<database::QueueQuery>| {
+                    async move {
+                        if let Some(queue) = orm.queue(user.sub, query).await {
+                            return Json(queue).into_response();
+                        }
+
+                        StatusCode::BAD_REQUEST.into_response()
+                    }
+                }
+            };
+            get: "/stats", (3, 5), {
+                let orm = orm.clone();
+                |Extension(user): Extension<KinoIdToken>, Query(query): Query<database::StatsQuery>| {
+                    async move {
+                        if let Some(stats) = orm.stats(user.sub, query).await {
+                            return Json(stats).into_response();
+                        }
+
+                        StatusCode::BAD_REQUEST.into_response()
+                    }
+                }
+            };
             get: "/cards/:id/done", (5, 1), {
-                let pg = Arc::clone(&self.pg);
+                let orm = orm.clone();
                 |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| {
                     async move {
-                        sqlx::query_scalar!(
-                            "UPDATE cards SET done_at = NOW() WHERE id = $1 AND owner_id = $2",
-                            id, user.sub
-                        )
-                            .execute(pg.borrow())
-                            .await
-                            .ok();
+                        orm.mark_done(&[id], user.sub).await;
+                    }
+                }
+            };
+            post: "/cards/:id/review", (5, 1), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>, Json(review): Json<database::ReviewRequest>| {
+                    async move {
+                        Json(orm.review_card(id, user.sub, review).await)
+                    }
+                }
+            };
+            get: "/cards/:id/reviews", (5, 5), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| {
+                    async move {
+                        Json(orm.card_reviews(id, user.sub).await)
+                    }
+                }
+            };
+            post: "/cards/:id/undo", (5, 5), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| {
+                    async move {
+                        Json(orm.undo_review(id, user.sub).await)
                     }
                 }
             };
             get: "/cards/:id/move", (5, 1), {
-                let pg = Arc::clone(&self.pg);
+                let orm = orm.clone();
                 |RawQuery(deck_id): RawQuery, Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| {
                     async move {
                         if let Some(deck_id) = deck_id {
                             if let Ok(deck_id) = deck_id.parse::<i64>() {
-                                sqlx::query_scalar!(
-                                    r#"UPDATE cards
-                                    SET 
-                                        done_at = NOW(), 
-                                        deck_id = $3 
-                                    WHERE 
-                                        id = $1 AND
-                                        owner_id = $2 AND 
-                                        EXISTS(SELECT 1 FROM decks WHERE id = $3 AND owner_id = $2)"#,
-                                    id, user.sub, deck_id
-                                )
-                                    .execute(pg.borrow())
-                                    .await
-                                    .ok();
+                                orm.move_card(id, user.sub, deck_id).await;
                             }
                         }
                     }
                 }
             };
+            post: "/cards/:id/edit", (5, 5), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>, Json(edit): Json<database::EditCard>| {
+                    async move {
+                        orm.edit_card(id, edit, user.sub).await.map(Json)
+                    }
+                }
+            };
             get: "/cards/:id/delete", (5, 1), {
-                let pg = Arc::clone(&self.pg);
+                let orm = orm.clone();
                 |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| {
                     async move {
-                        sqlx::query_scalar!(
-                            r#"WITH
-                                deleted_faces as (DELETE FROM cards WHERE id = $1 RETURNING front || back AS face_id)
-                            DELETE
-                                FROM faces
-                            WHERE
-                                EXISTS(SELECT true FROM deleted_faces WHERE faces.id = ANY(face_id)) AND
-                                faces.owner_id = $2"#,
-                            id, user.sub
-                        )
-                            .execute(pg.borrow())
-                            .await
-                            .ok();
+                        orm.delete_card(id, user.sub).await.map(Json)
+                    }
+                }
+            };
+            post: "/decks/new", (1, 5), {
+                let orm = orm.clone();
+                |Extension(user): Extension<KinoIdToken>, Json(deck): Json<database::CreateDeck>| {
+                    async move {
+                        if let Err(errors) = deck.validate() {
+                            return (StatusCode::BAD_REQUEST, Json(errors)).into_response();
+                        }
+
+                        Json(orm.create_deck(deck, user.sub).await).into_response()
+                    }
+                }
+            };
+            post: "/decks/:id/edit", (5, 5), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>, Json(deck): Json<database::EditDeck>| {
+                    async move {
+                        if let Err(errors) = deck.validate() {
+                            return (StatusCode::BAD_REQUEST, Json(errors)).into_response();
+                        }
+
+                        Json(orm.edit_deck(id, deck, user.sub).await).into_response()
+                    }
+                }
+            };
+            post: "/decks/reorder", (5, 5), {
+                let orm = orm.clone();
+                |Extension(user): Extension<KinoIdToken>, Json(ids): Json<Vec<i64>>| {
+                    async move {
+                        Json(orm.reorder_decks(&ids, user.sub).await)
+                    }
+                }
+            };
+            post: "/decks/recount", (1, 60), {
+                let orm = orm.clone();
+                |Extension(user): Extension<KinoIdToken>| {
+                    async move {
+                        Json(orm.recount_cards(user.sub).await)
+                    }
+                }
+            };
+            get: "/decks/:id/delete", (1, 5), {
+                let orm = orm.clone();
+                |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>, Query(options): Query<database::DeleteDeck>| {
+                    async move {
+                        Json(orm.delete_deck(id, options, user.sub).await.is_some())
                     }
                 }
             };
+            post: "/decks/:id/import", (1, 10), transfer::import!(self, orm);
+            get: "/decks/:id/export", (1, 10), transfer::export!(orm);
+            get: "/decks/:id/anki", (1, 30), transfer::anki_export!(self, orm);
+            get: "/account/export", (1, 60), account::export!(orm);
+            get: "/account/delete/token", (1, 10), account::delete_token!(self);
+            get: "/decks/:id/quiz", (2, 5), quiz::quiz!(self, orm);
+            post: "/quiz/grade", (2, 5), quiz::grade!(self, orm);
+            post: "/cards/bulk", (1, 5), cards::bulk!(self, orm);
             post: "/cards/new", (1, 5), {
                 let orm = orm.clone();
                 |Extension(user): Extension<KinoIdToken>, Json(card_options): Json<database::CreateCard>| {
                     async move {
-                        Json(orm.create_card(card_options, user.sub).await)
+                        orm.create_card(card_options, user.sub).await.map(Json)
                     }
                 }
             }
         };
 
+        // packages are larger than the default body limit
+        let uploads = self.auth(
+            routes! {
+                @limited
+                post: "/import/anki", (1, 60), transfer::anki!(self, orm);
+                post: "/account/import", (1, 60), account::import!(orm);
+            }
+            .layer(DefaultBodyLimit::max(PACKAGE_LIMIT)),
+        );
+
         public
+            .merge(jwks)
+            .merge(refresh)
+            .merge(account_deletion)
+            .merge(dictionary)
+            .merge(uploads)
             .merge(auth_required)
             .merge(restricted_data!(Deck, Card, Face, Extension))
     }
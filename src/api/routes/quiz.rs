@@ -0,0 +1,131 @@
+use crate::api::{
+    quiz::{PendingQuestion, Question},
+    Server,
+};
+
+/// Seconds an issued question can be answered for.
+const QUESTION_LIFETIME: u64 = 3600;
+
+impl Server {
+    /// Keeps answers of questions until they are answered.
+    pub(super) fn issue_questions(&self, user_id: i64, questions: &[Question]) -> bool {
+        let mut pipe = redis::pipe();
+        for question in questions {
+            let Ok(pending) = serde_json::to_string(&PendingQuestion::from(question)) else {
+                return false;
+            };
+            pipe.set_ex(
+                format!("quiz:{user_id}:{}", question.id),
+                pending,
+                QUESTION_LIFETIME,
+            )
+            .ignore();
+        }
+
+        pipe.query::<()>(&mut self.redis.lock().unwrap()).is_ok()
+    }
+
+    /// Removes issued questions of user, so that each question is answered once.
+    pub(super) fn take_questions(&self, user_id: i64, ids: &[i64]) -> Vec<Option<PendingQuestion>> {
+        if ids.is_empty() {
+            return vec![];
+        }
+
+        let keys = ids
+            .iter()
+            .map(|id| format!("quiz:{user_id}:{id}"))
+            .collect::<Vec<_>>();
+        let Ok((questions,)) = redis::pipe()
+            .atomic()
+            .cmd("MGET")
+            .arg(&keys)
+            .del(&keys)
+            .ignore()
+            .query::<(Vec<Option<String>>,)>(&mut self.redis.lock().unwrap())
+        else {
+            return vec![None; ids.len()];
+        };
+
+        questions
+            .into_iter()
+            .map(|question| serde_json::from_str(&question?).ok())
+            .collect()
+    }
+}
+
+macro_rules! quiz {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::quiz::Quiz;
+
+        use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+
+        move |Path(deck_id): Path<i64>, Extension(user): Extension<KinoIdToken>| async move {
+            let cards = orm.deck_quiz_cards(deck_id, user.sub).await;
+
+            let mut quiz = Quiz::new(&server.wordnet, server.snowflake.gen_id() as u64);
+            let questions = quiz.questions(&cards, || server.snowflake.gen_id());
+            if !server.issue_questions(user.sub, &questions) {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+
+            Json(questions).into_response()
+        }
+    }};
+}
+
+macro_rules! grade {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::{
+            database::ReviewRequest,
+            quiz::{Answer, Grade, Quiz},
+            scheduler::ReviewGrade,
+        };
+
+        use axum::{Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>, Json(mut answers): Json<Vec<Answer>>| async move {
+            // first answer of a question counts
+            let mut question_ids = Vec::new();
+            answers.retain(|answer| {
+                let first = !question_ids.contains(&answer.question_id);
+                question_ids.push(answer.question_id);
+                first
+            });
+            answers.truncate(Quiz::MAX_QUESTIONS);
+
+            let ids = answers.iter().map(|answer| answer.question_id).collect::<Vec<_>>();
+            let questions = server.take_questions(user.sub, &ids);
+
+            // each answer reviews its card, so that quizzes count in scheduling and statistics
+            let mut grades = Vec::with_capacity(answers.len());
+            for (answer, question) in answers.iter().zip(questions) {
+                let Some(question) = question else {
+                    continue;
+                };
+
+                let correct = Quiz::grade(&question, &answer.answer);
+                let review = ReviewRequest {
+                    grade: if correct {
+                        ReviewGrade::Good
+                    } else {
+                        ReviewGrade::Again
+                    },
+                    elapsed_ms: None,
+                };
+                if let Some(review) = orm.review_card(question.card_id, user.sub, review).await {
+                    grades.push(Grade { correct, review });
+                }
+            }
+
+            Json(grades)
+        }
+    }};
+}
+
+pub(crate) use {grade, quiz};
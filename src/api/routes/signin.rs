@@ -1,34 +1,42 @@
 use crate::{
     api::{
         database::Orm,
-        jwt::{KinoIdToken, KinoTokenScope},
+        jwt::{expires_after, KinoIdToken, KinoTokenScope, AUTH_TOKEN_LIFETIME},
         snowflake::Snowflake,
+        Server,
     },
-    google_signin::GoogleIdToken,
+    oidc::IdToken,
 };
 
 use sqlx::PgPool;
 
-use std::{
-    borrow::Borrow,
-    sync::Arc,
-    time::{Duration, SystemTime},
+use std::{borrow::Borrow, sync::Arc};
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
 
+use serde_json::json;
+
+/// Signs in the user of identity, or signs up with its verified `email`.
 pub(super) async fn login_or_signup(
-    token: GoogleIdToken,
+    token: IdToken,
+    email: String,
     database: &Arc<PgPool>,
     snowflake: &Arc<Snowflake>,
-) -> Option<KinoIdToken> {
-    let exp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0))
-        .as_secs()
-        + 3600 * 24 * 30;
-    let email = token.email.unwrap();
-    // check if user already exists
+) -> Result<KinoIdToken, StatusCode> {
+    let exp = expires_after(AUTH_TOKEN_LIFETIME);
+    // check if identity is already linked to a user
     let data = sqlx::query!(
-        "SELECT id, username, email FROM users WHERE google_id = $1;",
+        r#"
+            SELECT users.id, users.username, users.email
+            FROM identities
+            JOIN users ON users.id = identities.user_id
+            WHERE identities.issuer = $1 AND identities.subject = $2
+        "#,
+        token.iss,
         token.sub
     )
     .fetch_one(database.borrow())
@@ -36,74 +44,150 @@ pub(super) async fn login_or_signup(
 
     if let Ok(data) = data {
         tracing::debug!("User log in: id={} email={}", data.id, data.email);
-        return Some(KinoIdToken {
+        return Ok(KinoIdToken {
             sub: data.id,
             scope: vec![KinoTokenScope::Auth],
-            google_id: token.sub,
             email: data.email,
             username: data.username,
             exp,
+            ..Default::default()
         });
     }
 
     let id = snowflake.gen_id();
-    let Ok(_) =
-        sqlx::query_scalar!(
-            r#"
-            INSERT INTO users SELECT $1, $2, $3, NULL, $4, $5 WHERE
-                NOT EXISTS (SELECT 1 FROM users WHERE id = $1) AND
-                NOT EXISTS (SELECT 1 FROM users WHERE email = CAST($2 AS character varying(254))) AND
-                NOT EXISTS (SELECT 1 FROM users WHERE google_id = CAST($3 AS text))
+    let mut tx = database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // accounts with the same email are not linked automatically, users link them after signing in
+    let inserted = sqlx::query_scalar!(
+        r#"
+            INSERT INTO users SELECT $1, $2, NULL, $3, $4 WHERE
+                NOT EXISTS (SELECT 1 FROM users WHERE email = CAST($2 AS character varying(254)))
                 RETURNING true
-            "#,
-            id, email, token.sub, token.name, token.picture
-        ).fetch_one(database.borrow())
-        .await else {
-            // this block should be unreachable
-            return None
-        };
+        "#,
+        id,
+        email,
+        token.name,
+        token.picture
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if inserted.is_none() {
+        return Err(StatusCode::CONFLICT);
+    }
+    sqlx::query!(
+        r#"
+            INSERT INTO identities (id, user_id, issuer, subject, email, created_at)
+            VALUES ($1, $2, $3, $4, $5, LOCALTIMESTAMP)
+        "#,
+        snowflake.gen_id(),
+        id,
+        token.iss,
+        token.sub,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::CONFLICT)?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     let orm = Orm::new(Arc::clone(database), Arc::clone(snowflake));
     orm.default_decks(id).await;
 
     tracing::debug!("User sign up: id={} email={}", id, email);
-    Some(KinoIdToken {
+    Ok(KinoIdToken {
         sub: id,
         scope: vec![KinoTokenScope::Auth],
-        google_id: token.sub,
         email,
         username: None,
         exp,
+        ..Default::default()
+    })
+}
+
+/// Signs in with an ID token of provider, starting a session.
+pub(super) async fn sign_in(
+    server: &Server,
+    provider: &str,
+    token: Option<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(token) = token else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let Ok(token) = server.oidc.validate(provider, &token) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    // accounts are keyed by email, tokens without a verified one cannot sign in
+    let Some(email) = token
+        .email
+        .clone()
+        .filter(|_| token.email_verified == Some(true))
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let kino_token = match login_or_signup(token, email, &server.pg, &server.snowflake).await {
+        Ok(kino_token) => kino_token,
+        Err(StatusCode::CONFLICT) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({ "error": "email_in_use" })),
+            )
+                .into_response()
+        }
+        Err(status) => return status.into_response(),
+    };
+
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let orm = Orm::new(Arc::clone(&server.pg), Arc::clone(&server.snowflake));
+    let Some((sid, refresh_token)) = orm
+        .issue_refresh_token(
+            kino_token.sub,
+            header(header::USER_AGENT.as_str()),
+            header("X-Real-IP"),
+        )
+        .await
+    else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Some(token) = server.issue_token(KinoIdToken { sid, ..kino_token }) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    Json(super::tokens::TokenPair {
+        token,
+        refresh_token,
     })
+    .into_response()
 }
 
 macro_rules! signin {
     ($server:expr) => {{
-        let google_client = Arc::clone(&$server.google_client);
-        let pg = Arc::clone(&$server.pg);
-        let snowflake = Arc::clone(&$server.snowflake);
-
-        let kino_client = Arc::clone(&$server.kino_client);
-
-        use axum::{extract::RawQuery, http::StatusCode, response::IntoResponse, Json};
-
-        |RawQuery(token): RawQuery| async move {
-            let Some(token) = token else {
-                return StatusCode::BAD_REQUEST.into_response();
-            };
-
-            if let Ok(token) = google_client.validate(&token) {
-                if let Some(email_verified) = token.email_verified {
-                    if email_verified && token.email.is_some() {
-                        if let Some(kino_token) =
-                            signin::login_or_signup(token, &pg, &snowflake).await
-                        {
-                            return Json(kino_client.encode(kino_token)).into_response();
-                        }
-                    }
-                }
-            }
-
-            StatusCode::UNAUTHORIZED.into_response()
+        let server = Arc::clone($server);
+
+        use axum::{
+            extract::{Path, RawQuery},
+            http::HeaderMap,
+        };
+
+        move |Path(provider): Path<String>, RawQuery(token): RawQuery, headers: HeaderMap| async move {
+            signin::sign_in(&server, &provider, token, headers).await
+        }
+    }};
+    ($server:expr, $provider:expr) => {{
+        let server = Arc::clone($server);
+
+        use axum::{extract::RawQuery, http::HeaderMap};
+
+        move |RawQuery(token): RawQuery, headers: HeaderMap| async move {
+            signin::sign_in(&server, $provider, token, headers).await
         }
     }};
 }
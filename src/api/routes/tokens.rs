@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+/// Access token with the refresh token to renew it.
+#[derive(Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+macro_rules! refresh {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::{
+            database::RefreshError,
+            jwt::{expires_after, KinoTokenScope, AUTH_TOKEN_LIFETIME},
+        };
+
+        use axum::{http::StatusCode, response::IntoResponse, Json};
+
+        move |Json(request): Json<tokens::RefreshRequest>| async move {
+            let (refresh_token, owner) =
+                match orm.rotate_refresh_token(&request.refresh_token).await {
+                    Ok(rotated) => rotated,
+                    Err(error) => {
+                        // access tokens of the session would otherwise stay valid until they expire
+                        if let RefreshError::TokenReused(session_id) = error {
+                            if !server.revoke_session(session_id) {
+                                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                            }
+                        }
+                        return error.into_response();
+                    }
+                };
+
+            let token = server.issue_token(KinoIdToken {
+                sub: owner.id,
+                scope: vec![KinoTokenScope::Auth],
+                email: owner.email,
+                username: owner.username,
+                exp: expires_after(AUTH_TOKEN_LIFETIME),
+                sid: owner.session_id,
+                ..Default::default()
+            });
+
+            match token {
+                Some(token) => Json(tokens::TokenPair {
+                    token,
+                    refresh_token,
+                })
+                .into_response(),
+                None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! logout {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use axum::{http::StatusCode, Extension};
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            // the token itself is revoked too, as tokens issued before sessions have none
+            if orm.revoke_session(user.sid, user.sub).await.is_some()
+                && server.revoke_session(user.sid)
+                && server.revoke_token(&user)
+            {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }};
+}
+
+macro_rules! logout_all {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use axum::{http::StatusCode, Extension};
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            // sessions go first, so that no new access tokens are issued after revocation
+            if orm.revoke_sessions(user.sub).await.is_some() && server.revoke_user(user.sub) {
+                StatusCode::NO_CONTENT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }};
+}
+
+macro_rules! sessions {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use axum::{Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>| async move {
+            Json(orm.sessions(user.sub, user.sid).await)
+        }
+    }};
+}
+
+macro_rules! revoke_session {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use axum::{extract::Path, http::StatusCode, Extension};
+
+        move |Path(id): Path<i64>, Extension(user): Extension<KinoIdToken>| async move {
+            match orm.revoke_session(id, user.sub).await {
+                Some(true) if server.revoke_session(id) => StatusCode::NO_CONTENT,
+                Some(false) => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        }
+    }};
+}
+
+pub(crate) use {logout, logout_all, refresh, revoke_session, sessions};
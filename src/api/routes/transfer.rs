@@ -0,0 +1,160 @@
+macro_rules! import {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use database::{ImportQuery, ImportRows, BULK_LIMIT};
+
+        use axum::{
+            extract::{Path, Query},
+            response::IntoResponse,
+            Extension, Json,
+        };
+
+        move |Path(deck_id): Path<i64>,
+              Extension(user): Extension<KinoIdToken>,
+              Query(query): Query<ImportQuery>,
+              text: String| async move {
+            let rows = ImportRows::parse(deck_id, &text, &query);
+
+            // imported rows share the quota of bulk card creation
+            if let Some(response) = server.limit_user_cost(
+                "cards_bulk",
+                user.sub,
+                rows.len(),
+                2 * BULK_LIMIT,
+                Duration::from_secs(600),
+            ) {
+                return response;
+            }
+
+            match orm.import_deck(deck_id, rows, user.sub).await {
+                Ok(report) => Json(report).into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! export {
+    ($orm:expr) => {{
+        let orm = $orm.clone();
+
+        use database::ExportQuery;
+
+        use axum::{
+            extract::{Path, Query},
+            http::header,
+            response::IntoResponse,
+            Extension,
+        };
+
+        move |Path(deck_id): Path<i64>,
+              Extension(user): Extension<KinoIdToken>,
+              Query(query): Query<ExportQuery>| async move {
+            let content_type = query.format.content_type();
+            match orm.export_deck(deck_id, query, user.sub).await {
+                Ok(table) => ([(header::CONTENT_TYPE, content_type)], table).into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! anki {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::anki::AnkiPackage;
+        use database::BULK_LIMIT;
+
+        use axum::{body::Bytes, response::IntoResponse, Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>, package: Bytes| async move {
+            let temp_path = std::env::temp_dir()
+                .join(format!("kino-anki-{}.sqlite", server.snowflake.gen_id()));
+            let package =
+                tokio::task::spawn_blocking(move || AnkiPackage::read(&package, temp_path))
+                    .await
+                    .unwrap();
+
+            let package = match package {
+                Ok(package) => package,
+                Err(error) => return error.into_response(),
+            };
+
+            // notes share the quota of bulk card creation
+            if let Some(response) = server.limit_user_cost(
+                "cards_bulk",
+                user.sub,
+                package.notes.len(),
+                2 * BULK_LIMIT,
+                Duration::from_secs(600),
+            ) {
+                return response;
+            }
+
+            match orm.import_anki(package, user.sub).await {
+                Ok(report) => Json(report).into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+macro_rules! anki_export {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use crate::api::anki::render_face;
+        use database::AnkiExportQuery;
+
+        use axum::{
+            extract::{Path, Query},
+            http::header,
+            response::IntoResponse,
+            Extension,
+        };
+
+        move |Path(deck_id): Path<i64>,
+              Extension(user): Extension<KinoIdToken>,
+              Query(query): Query<AnkiExportQuery>| async move {
+            let wordnet = Arc::clone(&server.wordnet);
+            let export = match orm
+                .export_anki(deck_id, query, user.sub, |extension_id, data| {
+                    render_face(&wordnet, extension_id, data)
+                })
+                .await
+            {
+                Ok(export) => export,
+                Err(error) => return error.into_response(),
+            };
+
+            let temp_path = std::env::temp_dir()
+                .join(format!("kino-anki-{}.sqlite", server.snowflake.gen_id()));
+            let now = chrono::Utc::now().timestamp();
+            let package = tokio::task::spawn_blocking(move || export.write(now, temp_path))
+                .await
+                .unwrap();
+
+            match package {
+                Ok(package) => (
+                    [
+                        (header::CONTENT_TYPE, "application/octet-stream".to_owned()),
+                        (
+                            header::CONTENT_DISPOSITION,
+                            format!("attachment; filename=\"{deck_id}.apkg\""),
+                        ),
+                    ],
+                    package,
+                )
+                    .into_response(),
+                Err(error) => error.into_response(),
+            }
+        }
+    }};
+}
+
+pub(crate) use {anki, anki_export, export, import};
@@ -1,5 +1,16 @@
+use crate::api::database::ProfileError;
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+
 use serde::{Deserialize, Serialize};
 
+use serde_json::json;
+
+use validator::{ValidationError, ValidationErrors};
+
 #[derive(Serialize, Deserialize)]
 pub struct User {
     pub id: i64,
@@ -8,6 +19,19 @@ pub struct User {
     pub picture: Option<String>,
 }
 
+impl IntoResponse for ProfileError {
+    fn into_response(self) -> Response {
+        // reported like validation errors of the field
+        if let Self::UsernameTaken = self {
+            let mut errors = ValidationErrors::new();
+            errors.add("username", ValidationError::new("taken"));
+            return (self.status(), Json(errors)).into_response();
+        }
+
+        (self.status(), Json(json!({ "error": self.code() }))).into_response()
+    }
+}
+
 macro_rules! user {
     ($database:expr) => {{
         use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
@@ -54,4 +78,38 @@ macro_rules! user {
     }};
 }
 
-pub(crate) use user;
+macro_rules! edit {
+    ($server:expr, $orm:expr) => {{
+        let server = Arc::clone($server);
+        let orm = $orm.clone();
+
+        use database::EditProfile;
+
+        use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+
+        use serde_json::json;
+
+        move |Extension(user): Extension<KinoIdToken>, Json(profile): Json<EditProfile>| async move {
+            if let Err(errors) = profile.validate() {
+                return (StatusCode::BAD_REQUEST, Json(errors)).into_response();
+            }
+
+            let profile = match orm.edit_profile(profile, user.sub).await {
+                Ok(profile) => profile,
+                Err(error) => return error.into_response(),
+            };
+
+            // tokens carry username, so that a changed username needs a new token
+            let token = (profile.username != user.username).then(|| {
+                server.issue_token(KinoIdToken {
+                    username: profile.username.clone(),
+                    ..user
+                })
+            });
+
+            Json(json!({ "profile": profile, "token": token.flatten() })).into_response()
+        }
+    }};
+}
+
+pub(crate) use {edit, user};
@@ -0,0 +1,144 @@
+use crate::dicts::{Gloassary, SynsetId};
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of lemmas and synsets in a batch request.
+pub const BATCH_LIMIT: usize = 64;
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    #[serde(default)]
+    pub lemmas: Vec<String>,
+    #[serde(default)]
+    pub synsets: Vec<String>,
+}
+
+/// Batch response. Items are in request order, `null` if not found.
+#[derive(Serialize)]
+pub struct BatchResponse<T, U> {
+    pub lemmas: Vec<Option<T>>,
+    pub synsets: Vec<Option<U>>,
+}
+
+#[derive(Serialize)]
+pub struct SynsetEntry<'a> {
+    pub id: SynsetId,
+    #[serde(flatten)]
+    pub glossary: Gloassary<'a>,
+}
+
+macro_rules! batch {
+    ($server:expr) => {{
+        let server = Arc::clone($server);
+
+        use crate::dicts::Database;
+        use wordnet::{BatchRequest, BatchResponse, SynsetEntry, BATCH_LIMIT};
+
+        use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+
+        move |Extension(user): Extension<KinoIdToken>, Json(request): Json<BatchRequest>| async move {
+            let cost = request.lemmas.len() + request.synsets.len();
+            if cost == 0 || cost > BATCH_LIMIT {
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+
+            // 5 words per 2 seconds, same as `/wn/get`
+            if let Some(response) =
+                server.limit_user_cost("wn_batch", user.sub, cost, 5 * 40, Duration::from_secs(80))
+            {
+                return response;
+            }
+
+            let wordnet = &server.wordnet;
+            Json(BatchResponse {
+                lemmas: request
+                    .lemmas
+                    .iter()
+                    .map(|lemma| {
+                        if lemma.len() < 24 {
+                            wordnet.get(lemma)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+                synsets: request
+                    .synsets
+                    .iter()
+                    .map(|id| {
+                        let id = id.parse().ok()?;
+                        Some(SynsetEntry {
+                            id,
+                            glossary: wordnet.synset(id)?,
+                        })
+                    })
+                    .collect(),
+            })
+            .into_response()
+        }
+    }};
+}
+
+#[derive(Deserialize)]
+pub struct BundleQuery {
+    /// Dictionary version that client already has.
+    pub from: Option<String>,
+}
+
+macro_rules! bundle {
+    ($server:expr) => {{
+        let server = Arc::clone($server);
+
+        use wordnet::BundleQuery;
+
+        use axum::{
+            extract::Query,
+            http::{header, StatusCode},
+            response::IntoResponse,
+        };
+
+        move |Query(query): Query<BundleQuery>| async move {
+            let bundle = match query.from {
+                Some(from) if from == server.wordnet.version() => {
+                    return StatusCode::NOT_MODIFIED.into_response()
+                }
+                // full bundle is sent if delta is not available
+                Some(from) => match server.bundles.delta(&from).await {
+                    Some(delta) => delta,
+                    None => server.bundles.full().await,
+                },
+                None => server.bundles.full().await,
+            };
+
+            ([(header::CONTENT_TYPE, "application/octet-stream")], bundle).into_response()
+        }
+    }};
+}
+
+/// Maximum length of text to analyze in bytes.
+pub const ANALYZE_LIMIT: usize = 4096;
+
+#[derive(Deserialize)]
+pub struct AnalyzeRequest {
+    pub text: String,
+}
+
+macro_rules! analyze {
+    ($server:expr) => {{
+        let wordnet = Arc::clone(&$server.wordnet);
+
+        use wordnet::{AnalyzeRequest, ANALYZE_LIMIT};
+
+        use axum::{http::StatusCode, response::IntoResponse, Json};
+
+        move |Json(request): Json<AnalyzeRequest>| async move {
+            if request.text.len() > ANALYZE_LIMIT {
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+
+            Json(wordnet.analyze(&request.text)).into_response()
+        }
+    }};
+}
+
+pub(crate) use {analyze, batch, bundle};
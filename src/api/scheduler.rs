@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use chrono::{Duration, NaiveDateTime};
+
+/// Scheduling algorithm of a deck.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "scheduler", rename_all = "lowercase")]
+pub enum Scheduler {
+    /// Cards move between decks of increasing intervals, due time is deck interval after
+    /// `done_at`.
+    Leitner,
+    /// SuperMemo 2, each card has its own ease and interval.
+    Sm2,
+}
+
+/// How well a card is remembered in a review.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "review_grade", rename_all = "lowercase")]
+pub enum ReviewGrade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+/// Per-card scheduling state stored in `cards` table.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct CardSchedule {
+    pub due_at: Option<NaiveDateTime>,
+    /// SM-2 easiness factor, at least [`CardSchedule::MIN_EASE`].
+    pub ease: f64,
+    pub interval_days: f64,
+    /// Successful reviews in a row.
+    pub repetitions: i32,
+    /// Number of times the card is forgotten.
+    pub lapses: i32,
+}
+
+impl ReviewGrade {
+    /// SM-2 quality of response, between 0 and 5.
+    pub fn quality(self) -> f64 {
+        match self {
+            Self::Again => 1.0,
+            Self::Hard => 3.0,
+            Self::Good => 4.0,
+            Self::Easy => 5.0,
+        }
+    }
+
+    /// Leitner level of the card after review.
+    pub fn leitner_level(self, level: i32) -> i32 {
+        match self {
+            Self::Again => 0,
+            Self::Hard => level,
+            Self::Good => level + 1,
+            Self::Easy => level + 2,
+        }
+    }
+}
+
+impl Default for CardSchedule {
+    fn default() -> Self {
+        Self {
+            due_at: None,
+            ease: Self::INITIAL_EASE,
+            interval_days: 0.0,
+            repetitions: 0,
+            lapses: 0,
+        }
+    }
+}
+
+impl CardSchedule {
+    pub const INITIAL_EASE: f64 = 2.5;
+    pub const MIN_EASE: f64 = 1.3;
+    /// Interval of a forgotten card.
+    pub const RELEARN_INTERVAL_DAYS: f64 = 10.0 / (24.0 * 60.0);
+
+    /// Next schedule with SM-2 after a review at `now`.
+    pub fn review(&self, grade: ReviewGrade, now: NaiveDateTime) -> Self {
+        let quality = grade.quality();
+        let mut next = *self;
+
+        next.ease = (self.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02))
+            .max(Self::MIN_EASE);
+
+        if grade == ReviewGrade::Again {
+            next.repetitions = 0;
+            next.lapses += 1;
+            next.interval_days = Self::RELEARN_INTERVAL_DAYS;
+        } else {
+            next.repetitions += 1;
+            next.interval_days = match next.repetitions {
+                1 => 1.0,
+                2 => 6.0,
+                _ => (self.interval_days * next.ease).round(),
+            };
+            if grade == ReviewGrade::Hard {
+                next.interval_days = (next.interval_days * 0.8).max(1.0).round()
+            }
+        }
+
+        next.due_at = Some(now + Duration::seconds((next.interval_days * 86400.0) as i64));
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        chrono::DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc()
+    }
+
+    fn learned(repetitions: i32, interval_days: f64, ease: f64) -> CardSchedule {
+        CardSchedule {
+            ease,
+            interval_days,
+            repetitions,
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn good_keeps_ease_and_follows_first_intervals() {
+        let first = CardSchedule::default().review(ReviewGrade::Good, now());
+        assert_close(first.ease, 2.5);
+        assert_eq!(first.repetitions, 1);
+        assert_close(first.interval_days, 1.0);
+        assert_eq!(first.due_at, Some(now() + Duration::days(1)));
+
+        let second = first.review(ReviewGrade::Good, now());
+        assert_eq!(second.repetitions, 2);
+        assert_close(second.interval_days, 6.0);
+
+        let third = second.review(ReviewGrade::Good, now());
+        assert_eq!(third.repetitions, 3);
+        assert_close(third.interval_days, 15.0);
+    }
+
+    #[test]
+    fn easy_raises_ease() {
+        let next = learned(2, 6.0, 2.5).review(ReviewGrade::Easy, now());
+        assert_close(next.ease, 2.6);
+        assert_close(next.interval_days, 16.0);
+    }
+
+    #[test]
+    fn hard_lowers_ease_and_shortens_interval() {
+        let next = learned(2, 6.0, 2.5).review(ReviewGrade::Hard, now());
+        assert_close(next.ease, 2.36);
+        assert_eq!(next.repetitions, 3);
+        // round(6 * 2.36) shortened by a fifth
+        assert_close(next.interval_days, 11.0);
+
+        let first = CardSchedule::default().review(ReviewGrade::Hard, now());
+        assert_close(first.interval_days, 1.0);
+    }
+
+    #[test]
+    fn again_resets_repetitions_and_counts_lapse() {
+        let next = learned(3, 15.0, 2.5).review(ReviewGrade::Again, now());
+        assert_close(next.ease, 1.96);
+        assert_eq!(next.repetitions, 0);
+        assert_eq!(next.lapses, 1);
+        assert_close(next.interval_days, CardSchedule::RELEARN_INTERVAL_DAYS);
+        assert_eq!(next.due_at, Some(now() + Duration::minutes(10)));
+
+        // relearning starts from the first interval
+        let relearned = next.review(ReviewGrade::Good, now());
+        assert_eq!(relearned.repetitions, 1);
+        assert_close(relearned.interval_days, 1.0);
+        assert_eq!(relearned.lapses, 1);
+    }
+
+    #[test]
+    fn ease_does_not_drop_below_minimum() {
+        let next = learned(3, 15.0, CardSchedule::MIN_EASE).review(ReviewGrade::Again, now());
+        assert_close(next.ease, CardSchedule::MIN_EASE);
+
+        let next = learned(3, 15.0, 1.4).review(ReviewGrade::Hard, now());
+        assert_close(next.ease, CardSchedule::MIN_EASE);
+    }
+
+    #[test]
+    fn leitner_levels() {
+        assert_eq!(ReviewGrade::Again.leitner_level(3), 0);
+        assert_eq!(ReviewGrade::Hard.leitner_level(3), 3);
+        assert_eq!(ReviewGrade::Good.leitner_level(3), 4);
+        assert_eq!(ReviewGrade::Easy.leitner_level(3), 5);
+    }
+}
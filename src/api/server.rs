@@ -1,6 +1,13 @@
-use crate::{dicts::WordNetDatabase, google_signin::GoogleClient};
+use crate::{
+    dicts::WordNetDatabase,
+    oidc::{OidcProvider, OidcVerifier},
+};
 
-use super::{jwt::KinoClient, snowflake::Snowflake};
+use super::{
+    bundles::BundleStore,
+    jwt::{KeyRing, KinoClient},
+    snowflake::Snowflake,
+};
 
 use std::{
     path::PathBuf,
@@ -17,7 +24,8 @@ use redis::Client as RedisClient;
 /// Kino api web server struct for shared objects.
 pub struct Server {
     pub(crate) wordnet: Arc<WordNetDatabase>,
-    pub(crate) google_client: Arc<GoogleClient>,
+    pub(crate) bundles: Arc<BundleStore>,
+    pub(crate) oidc: Arc<OidcVerifier>,
     pub(crate) pg: Arc<Pool<Postgres>>,
     pub(crate) redis: Arc<Mutex<RedisClient>>,
     pub(crate) kino_client: Arc<KinoClient>,
@@ -26,18 +34,26 @@ pub struct Server {
 
 /// Configuration options for [`Server`]
 pub struct ServerBuilder<'a> {
-    pub google_audiences: Vec<String>,
-    pub google_allowed_hosted_domains: Vec<String>,
+    /// OpenID Connect providers users sign in with.
+    pub oidc_providers: Vec<OidcProvider>,
     pub wn_location: &'a str,
+    /// Directory to keep dictionary bundles to serve deltas between dictionary versions.
+    pub wn_bundles: Option<&'a str>,
     pub pg_url: &'a str,
     pub redis_url: &'a str,
     pub jwt_secret: &'a str,
+    /// JSON manifest of asymmetric signing keys.
+    pub jwt_keys: Option<&'a str>,
 }
 
 impl<'a> ServerBuilder<'a> {
     /// Builds [`Server`] from [`ServerBuilder`]
     pub async fn build(self) -> Arc<Server> {
         let wordnet = Arc::new(WordNetDatabase::new(PathBuf::from(self.wn_location)));
+        let bundles = Arc::new(BundleStore::new(
+            Arc::clone(&wordnet),
+            self.wn_bundles.map(PathBuf::from),
+        ));
 
         let pg = Arc::new(
             PgPoolOptions::new()
@@ -47,22 +63,26 @@ impl<'a> ServerBuilder<'a> {
                 .expect("Cannot connect to Postgres database."),
         );
 
+        // not flushed on startup, as it holds token revocations
         let redis =
             redis::Client::open(self.redis_url).expect("Cannot connect to Postgres database.");
-        redis::cmd("FLUSHALL")
-            .query::<()>(&mut redis.get_connection().unwrap())
-            .unwrap();
 
-        let mut google_client =
-            GoogleClient::new(self.google_audiences, self.google_allowed_hosted_domains);
-        google_client.init().await;
+        let oidc = OidcVerifier::new(self.oidc_providers);
+        oidc.init().await;
+
+        let mut kino_client = KinoClient::new(self.jwt_secret);
+        if let Some(jwt_keys) = self.jwt_keys {
+            kino_client = kino_client
+                .with_keys(KeyRing::load(jwt_keys).expect("Cannot load JWT signing keys."));
+        }
 
         Arc::new(Server {
             wordnet,
-            google_client: Arc::new(google_client),
+            bundles,
+            oidc: Arc::new(oidc),
             pg,
             redis: Arc::new(Mutex::new(redis)),
-            kino_client: Arc::new(KinoClient::new(self.jwt_secret)),
+            kino_client: Arc::new(kino_client),
             snowflake: Snowflake::new(),
         })
     }
@@ -0,0 +1,39 @@
+use kino_api::dicts::{Bundle, WordNetDatabase};
+
+use std::{fs, path::PathBuf, process::exit};
+
+const USAGE: &str = "Usage:
+    wn_bundle export <WN_DATABASE> <OUTPUT>
+    wn_bundle delta <OLD_BUNDLE> <NEW_BUNDLE> <OUTPUT>";
+
+fn read_bundle(path: &str) -> Bundle {
+    let bytes = fs::read(path).unwrap_or_else(|_| panic!("Cannot read {path}"));
+    Bundle::decode(&bytes).unwrap_or_else(|_| panic!("{path} is not a valid bundle"))
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let args = args.iter().map(|arg| &arg[..]).collect::<Vec<_>>();
+
+    let (bundle, output) = match args[1..] {
+        ["export", wn_database, output] => {
+            let wordnet = WordNetDatabase::new(PathBuf::from(wn_database));
+            (wordnet.bundle(), output)
+        }
+        ["delta", old, new, output] => {
+            let (old, new) = (read_bundle(old), read_bundle(new));
+            if old.base.is_some() || new.base.is_some() {
+                eprintln!("Deltas can only be created from full bundles");
+                exit(1)
+            }
+            (Bundle::delta(&old, &new), output)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            exit(1)
+        }
+    };
+
+    fs::write(output, bundle.encode()).unwrap_or_else(|_| panic!("Cannot write {output}"));
+    println!("{output}: version {}", bundle.version);
+}
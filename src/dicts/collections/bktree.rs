@@ -29,6 +29,31 @@ impl BKTree {
         }
     }
 
+    /// Whether no word is inserted.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Nodes in preorder as (word, distance to parent, number of children). Distance of root is
+    /// 0. Tree can be rebuilt from nodes without computing distances.
+    pub fn nodes(&self) -> Vec<(&String, usize, usize)> {
+        let mut nodes = Vec::new();
+        let mut stack = self.root.iter().map(|root| (root, 0)).collect::<Vec<_>>();
+        while let Some((node, distance)) = stack.pop() {
+            let children = node
+                .children
+                .iter()
+                .flat_map(|(&distance, bknodes)| {
+                    bknodes.iter().map(move |bknode| (bknode, distance))
+                })
+                .collect::<Vec<_>>();
+            nodes.push((&node.word, distance, children.len()));
+            // first child is visited first
+            stack.extend(children.into_iter().rev());
+        }
+        nodes
+    }
+
     /// Find closest n words. Not perfect but enough.
     pub fn find<'a>(&'a self, word: &str, limit: usize) -> Vec<&'a String> {
         if let Some(root) = &self.root {
@@ -5,4 +5,6 @@ mod wordnet;
 pub mod collections;
 
 pub use database::Database;
-pub use wordnet::WordNetDatabase;
+pub use wordnet::{
+    Bundle, BundleSuggestion, BundleSynset, Gloassary, SynsetId, TextSpan, WordNetDatabase,
+};
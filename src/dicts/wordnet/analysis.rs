@@ -0,0 +1,322 @@
+use super::{SynsetId, WordNetDatabase};
+
+use serde::Serialize;
+
+/// Dictionary word found in a text.
+#[derive(Debug, Serialize)]
+pub struct TextSpan {
+    /// Start of the span in UTF-16 code units, as indexed by JavaScript strings.
+    pub start: usize,
+    /// End of the span (exclusive) in UTF-16 code units.
+    pub end: usize,
+    pub lemma: String,
+    /// Candidate senses of the lemma, most frequent first.
+    pub senses: Vec<SynsetId>,
+}
+
+// Word in text with UTF-16 range.
+struct Token<'a> {
+    word: String,
+    utf16_start: usize,
+    utf16_end: usize,
+    // text between previous token and this one
+    gap: &'a str,
+}
+
+impl WordNetDatabase {
+    /// Longest collocation that is matched in WordNet, e.g. `give_up`.
+    pub const MAX_COLLOCATION_WORDS: usize = 5;
+
+    // Detachment rules of WordNet's morphy, in WORD_TYPES order.
+    const SUFFIXES: [&'static [(&'static str, &'static str)]; 4] = [
+        &[
+            ("s", ""),
+            ("ses", "s"),
+            ("xes", "x"),
+            ("zes", "z"),
+            ("ches", "ch"),
+            ("shes", "sh"),
+            ("men", "man"),
+            ("ies", "y"),
+        ],
+        &[
+            ("s", ""),
+            ("ies", "y"),
+            ("es", "e"),
+            ("es", ""),
+            ("ed", "e"),
+            ("ed", ""),
+            ("ing", "e"),
+            ("ing", ""),
+        ],
+        &[("er", ""), ("est", ""), ("er", "e"), ("est", "e")],
+        &[],
+    ];
+
+    /// Returns true if lemma exists in any part of speech.
+    pub fn has_lemma(&self, lemma: &str) -> bool {
+        self.index.iter().any(|index| index.contains_key(lemma))
+    }
+
+    /// Base forms of an inflected lowercase word that exist in dictionary. Word itself comes
+    /// first if it is a lemma.
+    pub fn lemmatize(&self, word: &str) -> Vec<String> {
+        let mut lemmas = Vec::new();
+        let mut push = |lemma: &str| {
+            if self.has_lemma(lemma) && !lemmas.iter().any(|found| found == lemma) {
+                lemmas.push(lemma.to_string())
+            }
+        };
+
+        push(word);
+        for (pos, suffixes) in Self::SUFFIXES.iter().enumerate() {
+            if let Some(bases) = self.exceptions[pos].get(word) {
+                bases.iter().for_each(|base| push(base));
+            }
+
+            for (suffix, ending) in suffixes.iter() {
+                if let Some(stem) = word.strip_suffix(suffix) {
+                    if !stem.is_empty() {
+                        push(&format!("{stem}{ending}"))
+                    }
+                }
+            }
+        }
+
+        lemmas
+    }
+
+    /// Finds dictionary words in text. Longest collocations are matched greedily and inflected
+    /// forms are lemmatized.
+    pub fn analyze(&self, text: &str) -> Vec<TextSpan> {
+        let tokens = tokenize(text);
+        let mut spans = Vec::new();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            // collocations cannot span over punctuation
+            let mut len = 1;
+            while len < Self::MAX_COLLOCATION_WORDS
+                && i + len < tokens.len()
+                && tokens[i + len].gap.chars().all(char::is_whitespace)
+            {
+                len += 1
+            }
+
+            let found = (1..=len)
+                .rev()
+                .find_map(|len| Some((len, self.match_words(&tokens[i..i + len])?)));
+
+            if let Some((len, lemma)) = found {
+                spans.push(TextSpan {
+                    start: tokens[i].utf16_start,
+                    end: tokens[i + len - 1].utf16_end,
+                    senses: self.synsets(&lemma),
+                    lemma,
+                });
+                i += len
+            } else {
+                i += 1
+            }
+        }
+
+        spans
+    }
+
+    // Matches tokens as a lemma. Verb collocations inflect their first word (gave up) and noun
+    // collocations inflect their last word (ice creams).
+    fn match_words(&self, tokens: &[Token]) -> Option<String> {
+        let words = tokens
+            .iter()
+            .map(|token| &token.word[..])
+            .collect::<Vec<_>>();
+
+        let joined = words.join("_");
+        if self.has_lemma(&joined) {
+            return Some(joined);
+        }
+
+        let (first, rest) = words.split_first()?;
+        for lemma in self.lemmatize(first) {
+            let lemma = [&lemma[..]]
+                .iter()
+                .chain(rest)
+                .copied()
+                .collect::<Vec<_>>()
+                .join("_");
+            if self.has_lemma(&lemma) {
+                return Some(lemma);
+            }
+        }
+
+        let (last, init) = words.split_last()?;
+        for lemma in self.lemmatize(last) {
+            let lemma = init
+                .iter()
+                .copied()
+                .chain([&lemma[..]])
+                .collect::<Vec<_>>()
+                .join("_");
+            if self.has_lemma(&lemma) {
+                return Some(lemma);
+            }
+        }
+
+        None
+    }
+}
+
+// Splits text into lowercase words. Apostrophes and hyphens inside words are kept, as in
+// `o'clock` and `well-known`.
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '\'' || c == '-';
+
+    let mut tokens = Vec::new();
+    let mut utf16 = 0;
+    let mut previous_end = 0;
+    let mut start = None;
+
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if is_word_char(c) {
+            start.get_or_insert((i, utf16));
+        } else if let Some((token_start, utf16_start)) = start.take() {
+            // trim apostrophes and hyphens around the word
+            let word = &text[token_start..i];
+            let trimmed = word.trim_matches(['\'', '-']);
+            if !trimmed.is_empty() {
+                let offset = word.len() - word.trim_start_matches(['\'', '-']).len();
+                let token_start = token_start + offset;
+                let token_end = token_start + trimmed.len();
+                let utf16_start = utf16_start + word[..offset].encode_utf16().count();
+                let utf16_end = utf16_start + trimmed.encode_utf16().count();
+
+                tokens.push(Token {
+                    word: trimmed.to_lowercase(),
+                    utf16_start,
+                    utf16_end,
+                    gap: &text[previous_end..token_start],
+                });
+                previous_end = token_end;
+            }
+        }
+        utf16 += c.len_utf16();
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::dicts::collections::{BKTree, WordTrie};
+
+    use std::collections::BTreeMap;
+
+    // Dictionary with lemmas per part of speech and irregular verb inflections.
+    fn database(lemmas: [&[&str]; 4], verb_exceptions: &[(&str, &str)]) -> WordNetDatabase {
+        let index = lemmas
+            .iter()
+            .map(|lemmas| {
+                lemmas
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, lemma)| (lemma.to_string(), vec![offset]))
+                    .collect()
+            })
+            .collect();
+        let mut exceptions = vec![BTreeMap::new(); 4];
+        for (inflection, base) in verb_exceptions {
+            exceptions[1].insert(inflection.to_string(), vec![base.to_string()]);
+        }
+
+        WordNetDatabase {
+            database: vec![String::new(); 4],
+            word_trie: WordTrie::new(),
+            bktree: BKTree::new(),
+            index,
+            exceptions,
+            version: String::new(),
+        }
+    }
+
+    fn words(tokens: &[Token]) -> Vec<String> {
+        tokens.iter().map(|token| token.word.clone()).collect()
+    }
+
+    #[test]
+    fn tokenize_lowercases_and_trims_words() {
+        let tokens = tokenize("'Well-known' O'Clock -- done.");
+        assert_eq!(words(&tokens), ["well-known", "o'clock", "done"]);
+        assert_eq!((tokens[0].utf16_start, tokens[0].utf16_end), (1, 11));
+        assert_eq!(tokens[0].gap, "'");
+        assert_eq!(tokens[1].gap, "' ");
+        assert_eq!(tokens[2].gap, " -- ");
+    }
+
+    #[test]
+    fn tokenize_counts_utf16_code_units() {
+        let tokens = tokenize("🙂 café ok");
+        assert_eq!(words(&tokens), ["café", "ok"]);
+        assert_eq!((tokens[0].utf16_start, tokens[0].utf16_end), (3, 7));
+        assert_eq!((tokens[1].utf16_start, tokens[1].utf16_end), (8, 10));
+    }
+
+    #[test]
+    fn lemmatize_detaches_suffixes() {
+        let database = database(
+            [&["box", "city", "dog"], &["make", "walk"], &["big"], &[]],
+            &[],
+        );
+        assert_eq!(database.lemmatize("boxes"), ["box"]);
+        assert_eq!(database.lemmatize("cities"), ["city"]);
+        assert_eq!(database.lemmatize("dogs"), ["dog"]);
+        assert_eq!(database.lemmatize("making"), ["make"]);
+        assert_eq!(database.lemmatize("walked"), ["walk"]);
+        assert!(database.lemmatize("bigger").is_empty());
+        assert!(database.lemmatize("cats").is_empty());
+    }
+
+    #[test]
+    fn lemmatize_prefers_word_and_uses_exceptions() {
+        let database = database(
+            [&["walk", "go"], &["walk", "go"], &[], &[]],
+            &[("went", "go")],
+        );
+        assert_eq!(database.lemmatize("walk"), ["walk"]);
+        assert_eq!(database.lemmatize("walks"), ["walk"]);
+        assert_eq!(database.lemmatize("went"), ["go"]);
+    }
+
+    #[test]
+    fn analyze_matches_inflected_collocations() {
+        let database = database(
+            [
+                &["ice_cream", "ice", "cream"],
+                &["give_up", "give"],
+                &[],
+                &[],
+            ],
+            &[("gave", "give")],
+        );
+
+        let spans = database.analyze("She gave up ice creams.");
+        let lemmas = spans.iter().map(|span| &span.lemma[..]).collect::<Vec<_>>();
+        assert_eq!(lemmas, ["give_up", "ice_cream"]);
+        assert_eq!((spans[0].start, spans[0].end), (4, 11));
+        assert_eq!((spans[1].start, spans[1].end), (12, 22));
+        assert_eq!(spans[1].senses, [SynsetId { pos: 0, offset: 0 }]);
+    }
+
+    #[test]
+    fn analyze_does_not_match_over_punctuation() {
+        let database = database([&["ice_cream", "ice", "cream"], &[], &[], &[]], &[]);
+
+        let lemmas = database
+            .analyze("Ice, cream")
+            .into_iter()
+            .map(|span| span.lemma)
+            .collect::<Vec<_>>();
+        assert_eq!(lemmas, ["ice", "cream"]);
+    }
+}
@@ -0,0 +1,471 @@
+use super::{SynsetId, WordNetDatabase};
+
+use crate::dicts::collections::BKTree;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Read, Write},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+/// Downloadable dictionary package for offline clients.
+///
+/// A bundle without `base` contains the whole dictionary. A bundle with `base` is a delta that
+/// turns the dictionary of version `base` into `version`. Lemma keys are sorted, so that clients
+/// can prefix search them. Fuzzy suggestions need a BK-tree, which is slow to build on devices,
+/// so that it is shipped in `suggestions`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Bundle {
+    pub version: String,
+    pub base: Option<String>,
+    pub synsets: BTreeMap<String, BundleSynset>,
+    pub removed_synsets: Vec<String>,
+    /// Lemma to synset ids, most frequent sense first.
+    pub lemmas: BTreeMap<String, Vec<String>>,
+    pub removed_lemmas: Vec<String>,
+    /// Suggestion BK-tree nodes in preorder. A tree cannot be patched, so that a delta contains
+    /// the whole tree if it changed and none otherwise.
+    pub suggestions: Vec<BundleSuggestion>,
+}
+
+/// BK-tree node of a [`Bundle`]. Children of a node follow it in preorder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleSuggestion {
+    pub word: String,
+    /// Levenshtein distance to parent, 0 for root.
+    pub distance: usize,
+    pub children: usize,
+}
+
+/// Synset entry of a [`Bundle`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleSynset {
+    pub lemmas: Vec<String>,
+    pub meanings: String,
+    pub examples: Vec<String>,
+    /// (pointer symbol, target synset id) pairs.
+    pub pointers: Vec<(String, String)>,
+}
+
+impl Bundle {
+    /// Bundle file signature.
+    pub const MAGIC: &'static [u8; 6] = b"KINOWN";
+    /// Bundle encoding version, incremented on incompatible format changes.
+    pub const FORMAT_VERSION: u8 = 2;
+
+    /// Creates a delta bundle that updates `old` to `new`. Both must be full bundles.
+    pub fn delta(old: &Bundle, new: &Bundle) -> Bundle {
+        let mut delta = Bundle {
+            version: new.version.clone(),
+            base: Some(old.version.clone()),
+            ..Default::default()
+        };
+
+        for (id, synset) in &new.synsets {
+            if old.synsets.get(id) != Some(synset) {
+                delta.synsets.insert(id.clone(), synset.clone());
+            }
+        }
+        delta.removed_synsets = old
+            .synsets
+            .keys()
+            .filter(|id| !new.synsets.contains_key(*id))
+            .cloned()
+            .collect();
+
+        for (lemma, synsets) in &new.lemmas {
+            if old.lemmas.get(lemma) != Some(synsets) {
+                delta.lemmas.insert(lemma.clone(), synsets.clone());
+            }
+        }
+        delta.removed_lemmas = old
+            .lemmas
+            .keys()
+            .filter(|lemma| !new.lemmas.contains_key(*lemma))
+            .cloned()
+            .collect();
+
+        if old.suggestions != new.suggestions {
+            delta.suggestions = new.suggestions.clone();
+        }
+
+        delta
+    }
+
+    /// Encodes bundle as gzip compressed binary.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut header = Self::MAGIC.to_vec();
+        header.push(Self::FORMAT_VERSION);
+
+        let mut encoder = GzEncoder::new(header, Compression::best());
+        let mut body = Vec::new();
+
+        write_str(&mut body, &self.version);
+        write_str(&mut body, self.base.as_deref().unwrap_or(""));
+
+        write_varint(&mut body, self.synsets.len());
+        for (id, synset) in &self.synsets {
+            write_str(&mut body, id);
+            write_strs(&mut body, &synset.lemmas);
+            write_str(&mut body, &synset.meanings);
+            write_strs(&mut body, &synset.examples);
+            write_varint(&mut body, synset.pointers.len());
+            for (symbol, target) in &synset.pointers {
+                write_str(&mut body, symbol);
+                write_str(&mut body, target);
+            }
+        }
+        write_strs(&mut body, &self.removed_synsets);
+
+        write_varint(&mut body, self.lemmas.len());
+        for (lemma, synsets) in &self.lemmas {
+            write_str(&mut body, lemma);
+            write_strs(&mut body, synsets);
+        }
+        write_strs(&mut body, &self.removed_lemmas);
+
+        write_varint(&mut body, self.suggestions.len());
+        for node in &self.suggestions {
+            write_str(&mut body, &node.word);
+            write_varint(&mut body, node.distance);
+            write_varint(&mut body, node.children);
+        }
+
+        // writing to Vec cannot fail
+        encoder.write_all(&body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Decodes bundle encoded by [`Bundle::encode`].
+    pub fn decode(bytes: &[u8]) -> io::Result<Bundle> {
+        let header_len = Self::MAGIC.len() + 1;
+        if bytes.len() < header_len
+            || &bytes[..Self::MAGIC.len()] != Self::MAGIC
+            || bytes[Self::MAGIC.len()] != Self::FORMAT_VERSION
+        {
+            return Err(invalid_data());
+        }
+
+        let mut body = Vec::new();
+        GzDecoder::new(&bytes[header_len..]).read_to_end(&mut body)?;
+        let body = &mut &body[..];
+
+        let version = read_str(body)?;
+        let base = Some(read_str(body)?).filter(|base| !base.is_empty());
+
+        let mut synsets = BTreeMap::new();
+        for _ in 0..read_varint(body)? {
+            let id = read_str(body)?;
+            let lemmas = read_strs(body)?;
+            let meanings = read_str(body)?;
+            let examples = read_strs(body)?;
+            let mut pointers = Vec::new();
+            for _ in 0..read_varint(body)? {
+                pointers.push((read_str(body)?, read_str(body)?));
+            }
+
+            synsets.insert(
+                id,
+                BundleSynset {
+                    lemmas,
+                    meanings,
+                    examples,
+                    pointers,
+                },
+            );
+        }
+        let removed_synsets = read_strs(body)?;
+
+        let mut lemmas = BTreeMap::new();
+        for _ in 0..read_varint(body)? {
+            lemmas.insert(read_str(body)?, read_strs(body)?);
+        }
+        let removed_lemmas = read_strs(body)?;
+
+        let mut suggestions = Vec::new();
+        for _ in 0..read_varint(body)? {
+            suggestions.push(BundleSuggestion {
+                word: read_str(body)?,
+                distance: read_varint(body)?,
+                children: read_varint(body)?,
+            });
+        }
+
+        Ok(Bundle {
+            version,
+            base,
+            synsets,
+            removed_synsets,
+            lemmas,
+            removed_lemmas,
+            suggestions,
+        })
+    }
+}
+
+impl WordNetDatabase {
+    /// Packages whole dictionary into a [`Bundle`].
+    pub fn bundle(&self) -> Bundle {
+        let mut synsets = BTreeMap::new();
+        for (pos, database) in self.database.iter().enumerate() {
+            let mut offset = 0;
+            for line in database.split_inclusive('\n') {
+                // license lines start with spaces
+                if !line.starts_with(' ') {
+                    let id = SynsetId { pos, offset };
+                    if let Some(glossary) = self.synset(id) {
+                        synsets.insert(
+                            id.to_string(),
+                            BundleSynset {
+                                lemmas: glossary.lemmas().map(String::from).collect(),
+                                meanings: glossary.meanings().to_string(),
+                                examples: glossary
+                                    .examples()
+                                    .into_iter()
+                                    .map(String::from)
+                                    .collect(),
+                                pointers: self
+                                    .pointers(id)
+                                    .into_iter()
+                                    .map(|(symbol, target)| {
+                                        (symbol.to_string(), target.to_string())
+                                    })
+                                    .collect(),
+                            },
+                        );
+                    }
+                }
+                offset += line.len();
+            }
+        }
+
+        let lemmas: BTreeMap<String, Vec<String>> = self
+            .index
+            .iter()
+            .flat_map(|index| index.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|lemma| {
+                let synsets = self
+                    .synsets(lemma)
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect();
+                (lemma.clone(), synsets)
+            })
+            .collect();
+
+        // tree of the loaded dictionary is not built in debug mode
+        let mut bktree = BKTree::new();
+        if self.bktree.is_empty() {
+            for lemma in lemmas.keys() {
+                bktree.insert(lemma.clone());
+            }
+        }
+        let suggestions = if self.bktree.is_empty() {
+            &bktree
+        } else {
+            &self.bktree
+        }
+        .nodes()
+        .into_iter()
+        .map(|(word, distance, children)| BundleSuggestion {
+            word: word.clone(),
+            distance,
+            children,
+        })
+        .collect();
+
+        Bundle {
+            version: self.version.clone(),
+            base: None,
+            synsets,
+            removed_synsets: vec![],
+            lemmas,
+            removed_lemmas: vec![],
+            suggestions,
+        }
+    }
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid dictionary bundle.")
+}
+
+// LEB128 unsigned integer.
+fn write_varint(buf: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    write_varint(buf, s.len());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn write_strs(buf: &mut Vec<u8>, strs: &[String]) {
+    write_varint(buf, strs.len());
+    for s in strs {
+        write_str(buf, s);
+    }
+}
+
+fn read_varint(buf: &mut &[u8]) -> io::Result<usize> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = buf.split_first().ok_or_else(invalid_data)?;
+        *buf = rest;
+
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data())
+}
+
+fn read_str(buf: &mut &[u8]) -> io::Result<String> {
+    let len = read_varint(buf)?;
+    if buf.len() < len {
+        return Err(invalid_data());
+    }
+
+    let (s, rest) = buf.split_at(len);
+    *buf = rest;
+    String::from_utf8(s.to_vec()).map_err(|_| invalid_data())
+}
+
+fn read_strs(buf: &mut &[u8]) -> io::Result<Vec<String>> {
+    let len = read_varint(buf)?;
+    let mut strs = Vec::new();
+    for _ in 0..len {
+        strs.push(read_str(buf)?);
+    }
+    Ok(strs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synset(lemma: &str, meanings: &str) -> BundleSynset {
+        BundleSynset {
+            lemmas: vec![lemma.to_string()],
+            meanings: meanings.to_string(),
+            examples: vec![format!("a {lemma}")],
+            pointers: vec![("@".to_string(), "n00000001".to_string())],
+        }
+    }
+
+    fn suggestion(word: &str, distance: usize, children: usize) -> BundleSuggestion {
+        BundleSuggestion {
+            word: word.to_string(),
+            distance,
+            children,
+        }
+    }
+
+    fn bundle(version: &str, entries: &[(&str, &str, &str)]) -> Bundle {
+        let mut bundle = Bundle {
+            version: version.to_string(),
+            ..Default::default()
+        };
+        for &(id, lemma, meanings) in entries {
+            bundle
+                .synsets
+                .insert(id.to_string(), synset(lemma, meanings));
+            bundle
+                .lemmas
+                .insert(lemma.to_string(), vec![id.to_string()]);
+        }
+        bundle
+    }
+
+    #[test]
+    fn full_bundle_round_trip() {
+        let mut full = bundle(
+            "3.1",
+            &[
+                ("n00000001", "entity", "that which exists"),
+                ("n00000002", "thing", "an entity"),
+            ],
+        );
+        full.suggestions = vec![suggestion("entity", 0, 1), suggestion("thing", 6, 0)];
+
+        let encoded = full.encode();
+        assert!(encoded.starts_with(Bundle::MAGIC));
+        assert_eq!(Bundle::decode(&encoded).unwrap(), full);
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let mut old = bundle(
+            "3.0",
+            &[
+                ("n00000001", "entity", "that which exists"),
+                ("n00000002", "thing", "an entity"),
+            ],
+        );
+        old.suggestions = vec![suggestion("entity", 0, 1), suggestion("thing", 6, 0)];
+        let mut new = bundle(
+            "3.1",
+            &[
+                ("n00000001", "entity", "that which is perceived"),
+                ("n00000003", "object", "a thing"),
+            ],
+        );
+        new.suggestions = vec![suggestion("entity", 0, 1), suggestion("object", 6, 0)];
+
+        let delta = Bundle::delta(&old, &new);
+        assert_eq!(delta.version, "3.1");
+        assert_eq!(delta.base.as_deref(), Some("3.0"));
+        assert_eq!(
+            delta.synsets.keys().collect::<Vec<_>>(),
+            ["n00000001", "n00000003"]
+        );
+        assert_eq!(delta.removed_synsets, ["n00000002"]);
+        assert_eq!(delta.lemmas.keys().collect::<Vec<_>>(), ["object"]);
+        assert_eq!(delta.removed_lemmas, ["thing"]);
+        assert_eq!(delta.suggestions, new.suggestions);
+
+        assert_eq!(Bundle::decode(&delta.encode()).unwrap(), delta);
+    }
+
+    #[test]
+    fn delta_skips_unchanged_suggestions() {
+        let old = bundle("3.0", &[("n00000001", "entity", "that which exists")]);
+        let mut new = bundle("3.1", &[("n00000001", "entity", "that which exists")]);
+        new.suggestions = old.suggestions.clone();
+
+        let delta = Bundle::delta(&old, &new);
+        assert!(delta.synsets.is_empty());
+        assert!(delta.lemmas.is_empty());
+        assert!(delta.suggestions.is_empty());
+    }
+
+    #[test]
+    fn decode_rejects_invalid_bundles() {
+        let mut encoded = bundle("3.1", &[]).encode();
+        assert!(Bundle::decode(&encoded[..4]).is_err());
+
+        encoded[Bundle::MAGIC.len()] = Bundle::FORMAT_VERSION + 1;
+        assert!(Bundle::decode(&encoded).is_err());
+
+        let mut encoded = bundle("3.1", &[]).encode();
+        encoded.truncate(encoded.len() - 8);
+        assert!(Bundle::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 0x7f, 0x80, 300, usize::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(read_varint(&mut &buf[..]).unwrap(), value);
+        }
+    }
+}
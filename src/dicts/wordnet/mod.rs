@@ -1,6 +1,12 @@
+mod analysis;
+mod bundle;
+mod synset;
 mod word;
 
-use word::{Gloassary, Word};
+pub use analysis::TextSpan;
+pub use bundle::{Bundle, BundleSuggestion, BundleSynset};
+pub use synset::SynsetId;
+pub use word::{Gloassary, Word};
 
 use super::{
     collections::{BKTree, WordTrie},
@@ -16,6 +22,9 @@ pub struct WordNetDatabase {
     /// BKTree is not initialized to improve performance if debug mode enabled.
     bktree: BKTree,
     index: Vec<BTreeMap<String, Vec<usize>>>,
+    /// Irregular inflections to base forms, from optional `*.exc` files.
+    exceptions: Vec<BTreeMap<String, Vec<String>>>,
+    version: String,
 }
 
 impl WordNetDatabase {
@@ -27,6 +36,8 @@ impl WordNetDatabase {
         let mut word_trie = WordTrie::new();
         let mut bktree = BKTree::new();
         let mut index = Vec::with_capacity(4);
+        let mut exceptions = Vec::with_capacity(4);
+        let mut hash = FNV_OFFSET_BASIS;
         tracing::info!("BKTree disabled in debug mode");
 
         for file in Self::WORD_TYPES {
@@ -34,6 +45,7 @@ impl WordNetDatabase {
                 let mut location = location.clone();
                 location.push(format!("{file_type}.{file}"));
                 let data = fs::read_to_string(location).expect("Cannot open database");
+                hash = fnv1a(hash, data.as_bytes());
 
                 if file_type == "index" {
                     let mut btree = BTreeMap::new();
@@ -65,6 +77,22 @@ impl WordNetDatabase {
                     database.push(data)
                 }
             }
+
+            let mut location = location.clone();
+            location.push(format!("{file}.exc"));
+            let mut btree = BTreeMap::new();
+            if let Ok(data) = fs::read_to_string(location) {
+                hash = fnv1a(hash, data.as_bytes());
+                for line in data.lines() {
+                    let mut words = line.split_whitespace();
+                    if let Some(inflection) = words.next() {
+                        btree.insert(inflection.to_owned(), words.map(String::from).collect());
+                    }
+                }
+            } else {
+                tracing::info!("{file}.exc not found, irregular inflections disabled");
+            }
+            exceptions.push(btree);
         }
 
         Self {
@@ -72,11 +100,110 @@ impl WordNetDatabase {
             word_trie,
             bktree,
             index,
+            exceptions,
+            version: format!("{hash:016x}"),
         }
     }
 
-    // Low-level API for fetching a part of word data.
-    fn get_by_offset(&self, db: usize, offset: usize) -> Option<Gloassary> {
+    /// Hash of loaded database files. Changes only if dictionary data changes.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Synset ids of lemma, grouped in [`Self::WORD_TYPES`] order. Most frequent sense comes
+    /// first in each group.
+    pub fn synsets(&self, lemma: &str) -> Vec<SynsetId> {
+        let mut synsets = Vec::new();
+        for (pos, index) in self.index.iter().enumerate() {
+            if let Some(offsets) = index.get(lemma) {
+                // offsets are collected from end of the index line
+                synsets.extend(offsets.iter().rev().map(|&offset| SynsetId { pos, offset }));
+            }
+        }
+        synsets
+    }
+
+    /// Gets glossary of a synset. Returns `None` if id does not point to a synset.
+    pub fn synset(&self, id: SynsetId) -> Option<Gloassary<'_>> {
+        if !self.is_synset(id) {
+            return None;
+        }
+        self.get_by_offset(id.pos, id.offset)
+    }
+
+    /// Lemmas of a synset.
+    pub fn lemmas(&self, id: SynsetId) -> Vec<&str> {
+        if let Some(glossary) = self.synset(id) {
+            glossary.lemmas().collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Pointers of a synset as (pointer symbol, target synset) pairs.
+    pub fn pointers(&self, id: SynsetId) -> Vec<(&str, SynsetId)> {
+        if !self.is_synset(id) {
+            return vec![];
+        }
+
+        let (_, lemma_end) = self.lemma_span(id.pos, id.offset);
+        let mut fields = self.database[id.pos][lemma_end..].split(' ');
+        let pointer_cnt: usize = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .unwrap_or(0);
+
+        let mut pointers = Vec::with_capacity(pointer_cnt);
+        for _ in 0..pointer_cnt {
+            // pointer_symbol synset_offset pos source/target
+            let (Some(symbol), Some(offset), Some(pos), Some(_)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                break;
+            };
+
+            if let (Ok(offset), Some(pos)) = (offset.parse(), SynsetId::pos_from_char(pos)) {
+                pointers.push((symbol, SynsetId { pos, offset }))
+            }
+        }
+
+        pointers
+    }
+
+    /// Lemmas that share a hypernym with given synset, which are semantically close but not
+    /// synonyms.
+    pub fn sister_terms(&self, id: SynsetId) -> Vec<&str> {
+        let mut terms = Vec::new();
+        for (symbol, hypernym) in self.pointers(id) {
+            if symbol != "@" && symbol != "@i" {
+                continue;
+            }
+
+            for (symbol, hyponym) in self.pointers(hypernym) {
+                if (symbol == "~" || symbol == "~i") && hyponym != id {
+                    terms.extend(self.lemmas(hyponym));
+                }
+            }
+        }
+        terms
+    }
+
+    // Checks whether offset is beginning of a synset line in data file.
+    fn is_synset(&self, id: SynsetId) -> bool {
+        let Some(database) = self.database.get(id.pos) else {
+            return false;
+        };
+        let bytes = database.as_bytes();
+
+        // the first 29 lines are license, so that synsets cannot start at 0
+        id.offset > 0
+            && id.offset + 17 < bytes.len()
+            && bytes[id.offset - 1] == b'\n'
+            && database[id.offset..].starts_with(&format!("{:08} ", id.offset)[..])
+    }
+
+    // Byte range of synset words (including lex_id's) in a data line.
+    fn lemma_span(&self, db: usize, offset: usize) -> (usize, usize) {
         // skip first 17 bytes (synset_offset lex_filenum ss_type synset_cnt)
         let lemma_start = offset + 17;
         let mut lemma_end = lemma_start;
@@ -94,6 +221,14 @@ impl WordNetDatabase {
             lemma_end += 1
         }
 
+        (lemma_start, lemma_end)
+    }
+
+    // Low-level API for fetching a part of word data.
+    fn get_by_offset(&self, db: usize, offset: usize) -> Option<Gloassary<'_>> {
+        let (lemma_start, lemma_end) = self.lemma_span(db, offset);
+        let bytes = self.database[db].as_bytes();
+
         let mut glossary_start = lemma_end;
         while bytes[glossary_start - 1] != b'|' {
             glossary_start += 1
@@ -126,6 +261,17 @@ impl WordNetDatabase {
     }
 }
 
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+// 64-bit FNV-1a hash, stable across builds unlike `std::hash::DefaultHasher`.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 impl<'a> Database<'a, Word<'a>> for WordNetDatabase {
     /// Gets word data without copying any &str
     fn get(&'a self, query: &str) -> Option<Word<'a>> {
@@ -0,0 +1,58 @@
+use serde::{Serialize, Serializer};
+
+use std::{fmt, str::FromStr};
+
+/// Identifies a synset by its part of speech and byte offset in the data file.
+///
+/// Formatted as part of speech letter followed by 8 digit offset, e.g. `n02084071`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SynsetId {
+    /// Index of part of speech in [`super::WordNetDatabase::WORD_TYPES`].
+    pub pos: usize,
+    pub offset: usize,
+}
+
+impl SynsetId {
+    const POS_CHARS: [char; 4] = ['n', 'v', 'a', 'r'];
+
+    // Parses ss_type field of data files. Adjective satellites are adjectives.
+    pub(super) fn pos_from_char(pos: &str) -> Option<usize> {
+        match pos {
+            "n" => Some(0),
+            "v" => Some(1),
+            "a" | "s" => Some(2),
+            "r" => Some(3),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SynsetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:08}", Self::POS_CHARS[self.pos], self.offset)
+    }
+}
+
+impl FromStr for SynsetId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 9 || !s.is_char_boundary(1) {
+            return Err(());
+        }
+
+        let pos = Self::pos_from_char(&s[..1]).ok_or(())?;
+        let offset = s[1..].parse().map_err(|_| ())?;
+
+        Ok(Self { pos, offset })
+    }
+}
+
+impl Serialize for SynsetId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
@@ -28,6 +28,26 @@ impl<'a> Gloassary<'a> {
             examples: Examples(examles),
         }
     }
+
+    /// Definition of the synset, without examples.
+    pub fn meanings(&self) -> &'a str {
+        self.meanings
+    }
+
+    /// Usage examples of the synset.
+    pub fn examples(&self) -> Vec<&'a str> {
+        self.examples.split()
+    }
+
+    /// Words in the synset.
+    pub fn lemmas(&self) -> impl Iterator<Item = &'a str> {
+        // synonyms are stored as `word lex_id word lex_id `
+        self.synonyms
+            .0
+            .split(' ')
+            .step_by(2)
+            .filter(|word| !word.is_empty())
+    }
 }
 
 impl Serialize for Word<'_> {
@@ -100,33 +120,39 @@ impl Serialize for Synonyms<'_> {
     }
 }
 
-impl Serialize for Examples<'_> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut len = 1;
+impl<'a> Examples<'a> {
+    // Splits `; "example"; "example"` into examples without quotes.
+    fn split(&self) -> Vec<&'a str> {
+        let mut examples = Vec::new();
         let bytes = self.0.as_bytes();
-        for &byte in bytes {
-            if byte == b';' {
-                len += 1
-            }
-        }
-        let mut seq = serializer.serialize_seq(Some(len))?;
 
         let mut last_start = 0;
         for (i, &byte) in bytes.iter().enumerate().skip(2) {
             if byte == b';' {
-                seq.serialize_element(
-                    std::str::from_utf8(&bytes[(last_start + 3)..(i - 1)]).unwrap(),
-                )?;
+                examples.push(std::str::from_utf8(&bytes[(last_start + 3)..(i - 1)]).unwrap());
                 last_start = i;
             }
             if i == bytes.len() - 1 && last_start + 3 < i {
-                seq.serialize_element(std::str::from_utf8(&bytes[(last_start + 3)..i]).unwrap())?;
+                examples.push(std::str::from_utf8(&bytes[(last_start + 3)..i]).unwrap());
             }
         }
 
+        examples
+    }
+}
+
+impl Serialize for Examples<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let examples = self.split();
+        let mut seq = serializer.serialize_seq(Some(examples.len()))?;
+
+        for example in examples {
+            seq.serialize_element(example)?;
+        }
+
         seq.end()
     }
 }
@@ -1,8 +1,8 @@
 /// Dictionary utils.
 pub mod dicts;
 
-/// Provides a `Client` to verify Google's OAuth client id tokens.
-pub mod google_signin;
+/// Verifies ID tokens of OpenID Connect providers such as Google.
+pub mod oidc;
 
 /// Kino web API.
 pub mod api;
@@ -1,4 +1,4 @@
-use kino_api::api::ServerBuilder;
+use kino_api::{api::ServerBuilder, oidc::OidcProvider};
 
 use lazy_static::lazy_static;
 
@@ -22,14 +22,18 @@ macro_rules! env {
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    env![
-        HOST,
-        GOOGLE_CLIENT_ID,
-        WN_DATABASE,
-        DATABASE_URL,
-        REDIS_URL,
-        JWT_SECRET
-    ];
+    env![HOST, WN_DATABASE, DATABASE_URL, REDIS_URL, JWT_SECRET];
+
+    let wn_bundles = std::env::var("WN_BUNDLES").ok();
+    let jwt_keys = std::env::var("JWT_KEYS").ok();
+
+    let mut oidc_providers = match std::env::var("OIDC_PROVIDERS") {
+        Ok(path) => OidcProvider::load(&path).expect("Cannot load OpenID Connect providers."),
+        Err(_) => vec![],
+    };
+    if let Ok(google_client_id) = std::env::var("GOOGLE_CLIENT_ID") {
+        oidc_providers.push(OidcProvider::google(vec![google_client_id], vec![]));
+    }
 
     #[cfg(debug_assertions)]
     tracing_subscriber::fmt()
@@ -37,12 +41,13 @@ async fn main() {
         .init();
 
     let server = ServerBuilder {
-        google_audiences: vec![String::from(*GOOGLE_CLIENT_ID)],
-        google_allowed_hosted_domains: vec![],
+        oidc_providers,
         wn_location: *WN_DATABASE,
+        wn_bundles: wn_bundles.as_deref(),
         pg_url: *DATABASE_URL,
         redis_url: *REDIS_URL,
         jwt_secret: *JWT_SECRET,
+        jwt_keys: jwt_keys.as_deref(),
     }
     .build()
     .await;
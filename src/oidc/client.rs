@@ -0,0 +1,207 @@
+use super::{IdToken, OidcProvider};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+
+type DecodingKeyList = Arc<Mutex<Option<Vec<(DecodingKey, String)>>>>;
+
+/// Time server startup waits for keys of providers.
+const INIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ID Token validator of an OpenID Connect provider.
+pub struct OidcClient {
+    provider: OidcProvider,
+    decoding_keys: DecodingKeyList,
+}
+
+impl OidcClient {
+    pub fn new(provider: OidcProvider) -> Self {
+        assert!(
+            !provider.issuers.is_empty(),
+            "Provider {} has no issuers.",
+            provider.name
+        );
+
+        Self {
+            provider,
+            decoding_keys: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Spawns a background task and updates `decoding_keys` regularly. Returned receiver is
+    /// notified when first keys are received.
+    pub fn init(&self) -> tokio::sync::oneshot::Receiver<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+        let decoding_keys = Arc::clone(&self.decoding_keys);
+        let name = self.provider.name.clone();
+        let jwks_uri = self.provider.jwks_uri.clone();
+        tokio::spawn(async move {
+            let mut tx = Some(tx);
+            loop {
+                if let Ok(request) = reqwest::get(&jwks_uri).await {
+                    // parse Cache-Control header as std::time::Duration
+                    let max_age = if let Some(cache_control_value) = {
+                        if let Some(header) = request.headers().get("Cache-Control") {
+                            header.to_str().ok()
+                        } else {
+                            None
+                        }
+                    } {
+                        if let Some(cache_control) =
+                            cache_control::CacheControl::from_value(cache_control_value)
+                        {
+                            cache_control.max_age
+                        } else {
+                            None
+                        }
+                    } else {
+                        None
+                    };
+
+                    let Some(jwks) = request
+                        .text()
+                        .await
+                        .ok()
+                        .and_then(|text| serde_json::from_str::<JwkSet>(&text[..]).ok())
+                    else {
+                        tracing::warn!("Invalid JWKS: provider={name}");
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        continue;
+                    };
+
+                    // keys without id or of unsupported algorithms cannot be selected
+                    *decoding_keys.lock().unwrap() = Some(
+                        jwks.keys
+                            .iter()
+                            .filter_map(|jwk| {
+                                Some((DecodingKey::from_jwk(jwk).ok()?, jwk.common.key_id.clone()?))
+                            })
+                            .collect::<Vec<(DecodingKey, String)>>(),
+                    );
+
+                    // notify main thread when first certs received
+                    if let Some(tx) = tx.take() {
+                        tracing::info!("first JWK certs received: provider={name}");
+                        // startup may have stopped waiting
+                        let _ = tx.send(());
+                    } else {
+                        tracing::info!("JWK certs reloaded: provider={name}");
+                    }
+
+                    tokio::time::sleep(max_age.unwrap_or(Duration::from_secs(10))).await;
+                } else {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Validates provider's JWT
+    pub fn validate(&self, token: &str) -> Result<IdToken, super::Error> {
+        let Ok(header) = decode_header(token) else {
+            return Err(super::Error::InvalidHeader);
+        };
+
+        // HMAC tokens could be signed with a public key as their secret
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(super::Error::InvalidHeader);
+        }
+
+        // token must include key_id
+        let Some(token_key_id) = header.kid else {
+            return Err(super::Error::MissingKeyId);
+        };
+
+        let Some(ref decoding_keys) = *self.decoding_keys.lock().unwrap() else {
+            return Err(super::Error::ClientNotInitialized);
+        };
+
+        let decoding_key = 'decoding_key: {
+            for (decoding_key, key_id) in decoding_keys {
+                if *key_id == token_key_id {
+                    break 'decoding_key decoding_key;
+                }
+            }
+            return Err(super::Error::KeyIdNotFound);
+        };
+
+        let validation = {
+            let mut validation = Validation::new(header.alg);
+            validation.set_audience(&self.provider.audiences);
+            validation.set_issuer(&self.provider.issuers);
+            validation
+        };
+
+        let decoded_token = match decode::<IdToken>(token, decoding_key, &validation) {
+            Ok(decoded_token) => {
+                let mut decoded_token = decoded_token.claims;
+                if let Some(ref hosted_domain) = decoded_token.hd {
+                    if !self.provider.allowed_hosted_domains.contains(hosted_domain) {
+                        return Err(super::Error::InvalidHostedDomain);
+                    }
+                }
+                decoded_token.iss = self.provider.issuers[0].clone();
+                decoded_token
+            }
+            Err(validation_error) => return Err(super::Error::ValidationError(validation_error)),
+        };
+
+        Ok(decoded_token)
+    }
+}
+
+/// ID Token validators of providers by name.
+pub struct OidcVerifier {
+    clients: HashMap<String, OidcClient>,
+}
+
+impl OidcVerifier {
+    pub fn new(providers: Vec<OidcProvider>) -> Self {
+        Self {
+            clients: providers
+                .into_iter()
+                .map(|provider| (provider.name.clone(), OidcClient::new(provider)))
+                .collect(),
+        }
+    }
+
+    /// Initializes clients of all providers concurrently, waiting for their first keys up to
+    /// [`INIT_TIMEOUT`]. Tokens of providers that are late are rejected until their keys arrive.
+    pub async fn init(&self) {
+        let deadline = tokio::time::Instant::now() + INIT_TIMEOUT;
+        let receivers = self
+            .clients
+            .iter()
+            .map(|(name, client)| (name, client.init()))
+            .collect::<Vec<_>>();
+
+        for (name, received) in receivers {
+            if !matches!(
+                tokio::time::timeout_at(deadline, received).await,
+                Ok(Ok(()))
+            ) {
+                tracing::warn!("JWK certs not received in time: provider={name}");
+            }
+        }
+    }
+
+    /// Validates a JWT of the provider.
+    pub fn validate(&self, provider: &str, token: &str) -> Result<IdToken, super::Error> {
+        let Some(client) = self.clients.get(provider) else {
+            return Err(super::Error::UnknownProvider);
+        };
+
+        client.validate(token)
+    }
+}
@@ -9,6 +9,7 @@ pub enum Error {
     MissingKeyId,
     InvalidHostedDomain,
     ClientNotInitialized,
+    UnknownProvider,
     ValidationError(jsonwebtoken::errors::Error),
 }
 
@@ -27,6 +28,7 @@ impl fmt::Display for Error {
                 "User is not on a permitted restricted domainuser is on permitted hosted domain.",
             ),
             Self::ClientNotInitialized => f.write_str("Decoding keys not initialized."),
+            Self::UnknownProvider => f.write_str("Provider is not configured."),
             Self::ValidationError(validation_error) => validation_error.fmt(f),
         }
     }
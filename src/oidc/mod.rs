@@ -0,0 +1,9 @@
+mod client;
+mod error;
+mod payload;
+mod provider;
+
+pub use client::{OidcClient, OidcVerifier};
+pub use error::Error;
+pub use payload::IdToken;
+pub use provider::OidcProvider;
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// OpenID Connect ID Token
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IdToken {
+    /// issuer: Normalized to the first issuer of the provider after validation.
+    pub iss: String,
+    /// subject: Unique account id at the issuer.
+    pub sub: String,
+    /// issued at: Unix timestamp when issued.
+    pub iat: u64,
+    /// expiration time: Unix timestamp that token expires
+    pub exp: u64,
+
+    /// hosted domain: Represents a Google Workspace or Cloud organization account.
+    pub hd: Option<String>,
+
+    // These fields are only included when the user has granted the "profile" and "email"
+    // scopes to the application.
+    pub email: Option<String>,
+    /// Some providers, e.g. Apple, send it as a string.
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub locale: Option<String>,
+}
+
+fn deserialize_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bool {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Option::<Bool>::deserialize(deserializer)? {
+        Some(Bool::Bool(value)) => Some(value),
+        Some(Bool::String(value)) => Some(value == "true"),
+        None => None,
+    })
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+use std::{fs, io};
+
+const GOOGLE_CERTS_URI: &str = "https://www.googleapis.com/oauth2/v3/certs";
+
+/// OpenID Connect provider that signs ID tokens of its users.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProvider {
+    /// Name of the provider in routes, e.g. `google`.
+    pub name: String,
+    /// Accepted `iss` claims. The first one identifies users of the provider.
+    pub issuers: Vec<String>,
+    /// URL of the provider's JSON Web Key Set.
+    pub jwks_uri: String,
+    /// Client ids of the application at the provider.
+    pub audiences: Vec<String>,
+    /// Hosted domains to accept. Tokens with an `hd` claim of another domain are rejected.
+    #[serde(default)]
+    pub allowed_hosted_domains: Vec<String>,
+}
+
+impl OidcProvider {
+    /// Google with its certs URI and issuers.
+    pub fn google(audiences: Vec<String>, allowed_hosted_domains: Vec<String>) -> Self {
+        Self {
+            name: String::from("google"),
+            issuers: vec![
+                String::from("https://accounts.google.com"),
+                String::from("accounts.google.com"),
+            ],
+            jwks_uri: String::from(GOOGLE_CERTS_URI),
+            audiences,
+            allowed_hosted_domains,
+        }
+    }
+
+    /// Reads a JSON array of providers.
+    pub fn load(path: &str) -> io::Result<Vec<Self>> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}